@@ -15,6 +15,9 @@
 #![flux::defs {
     fn pow2(x:int) -> bool;
     fn size(n:int) -> bool { pow2(n) && 1 <= n }
+    fn ring_len(tail:int, head:int, cap:int) -> int { if tail <= head { head - tail } else { cap - tail + head } }
+    fn ring_add(idx:int, n:int, cap:int) -> int { if idx + n < cap { idx + n } else { idx + n - cap } }
+    fn ring_sub(idx:int, n:int, cap:int) -> int { if n <= idx { idx - n } else { cap + idx - n } }
   }]
 pub mod vec_deque;
 pub mod raw_vec;
@@ -373,6 +373,8 @@ impl<T, A: Allocator> RawVec<T, A> {
     ///
     /// Aborts on OOM.
     #[cfg(not(no_global_oom_handling))]
+    #[flux::trusted]
+    #[flux::sig(fn (self: &strg RawVec<T, A>, cap: usize) ensures self: RawVec<T, A>[cap])]
     pub fn shrink_to_fit(&mut self, cap: usize) {
         handle_reserve(self.shrink(cap));
     }
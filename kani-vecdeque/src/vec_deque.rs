@@ -16,8 +16,15 @@
 // use core::slice;
 use crate::raw_vec::RawVec;
 use std::alloc::{Allocator, Global};
+use std::cmp::Ordering;
 pub use std::collections::vec_deque::*;
-use std::{cmp, mem, ptr};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ops::{Index, IndexMut, Range, RangeBounds};
+use std::{cmp, mem, ptr, slice};
 
 #[flux::constant]
 const INITIAL_CAPACITY: usize = 7; // 2^3 - 1
@@ -86,6 +93,26 @@ impl<T> Default for VecDeque<T> {
     }
 }
 
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: Clone, A: Allocator + Clone> Clone for VecDeque<T, A> {
+    fn clone(&self) -> Self {
+        let mut deq = Self::with_capacity_in(self.len(), self.buf.allocator().clone());
+        deq.extend(self.iter().cloned());
+        deq
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+unsafe impl<#[may_dangle] T, A: Allocator> Drop for VecDeque<T, A> {
+    fn drop(&mut self) {
+        let (front, back) = self.as_mut_slices();
+        unsafe {
+            drop_slices(front, back);
+        }
+        // RawVec handles deallocation
+    }
+}
+
 impl<T, A: Allocator> VecDeque<T, A> {
     /// Marginally more convenient
     #[inline]
@@ -96,7 +123,7 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// Marginally more convenient
     #[inline]
     #[flux::trusted] // ZST funkiness
-    #[flux::sig(fn (self: &VecDeque<T,A>[@me]) -> usize{v : v == me.cap && size(v) })]
+    #[flux::sig(fn (self: &VecDeque<T,A>[@me]) -> usize{v : v == me.cap && size(v) && v <= MAXIMUM_ZST_CAPACITY })]
     fn cap(&self) -> usize {
         if mem::size_of::<T>() == 0 {
             // For zero sized types, we are always at maximum capacity
@@ -115,6 +142,41 @@ impl<T, A: Allocator> VecDeque<T, A> {
         }
     }
 
+    /// Moves an element out of the buffer
+    #[inline]
+    #[flux::sig(fn (self: &VecDeque<T,A>[@me], off: usize{ off < me.cap }) -> T)]
+    unsafe fn buffer_read(&self, off: usize) -> T {
+        unsafe { ptr::read(self.ptr().add(off)) }
+    }
+
+    /// Borrows an element of the buffer.
+    ///
+    /// `off < cap` keeps the pointer inside the allocation; callers must also
+    /// make sure the slot holds a live element.
+    #[inline]
+    #[flux::trusted] // ptr
+    #[flux::sig(fn (self: &VecDeque<T,A>[@me], off: usize{ off < me.cap }) -> &T)]
+    unsafe fn buffer_ref(&self, off: usize) -> &T {
+        unsafe { &*self.ptr().add(off) }
+    }
+
+    /// Mutably borrows an element of the buffer. See `buffer_ref`.
+    #[inline]
+    #[flux::trusted] // ptr
+    #[flux::sig(fn (self: &mut VecDeque<T,A>[@me], off: usize{ off < me.cap }) -> &mut T)]
+    unsafe fn buffer_mut(&mut self, off: usize) -> &mut T {
+        unsafe { &mut *self.ptr().add(off) }
+    }
+
+    /// Swaps two elements of the buffer.
+    #[inline]
+    #[flux::sig(fn (self: &mut VecDeque<T,A>[@me], a: usize{ a < me.cap }, b: usize{ b < me.cap }))]
+    unsafe fn buffer_swap(&mut self, a: usize, b: usize) {
+        unsafe {
+            ptr::swap(self.ptr().add(a), self.ptr().add(b));
+        }
+    }
+
     /// Returns `true` if the buffer is at full capacity.
     #[inline]
     fn is_full(&self) -> bool {
@@ -124,17 +186,19 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// Returns the index in the underlying buffer for a given logical element
     /// index + addend.
     #[inline]
-    #[flux::sig(fn (self: &VecDeque<T,A>[@me], idx: usize, addend: usize) -> usize{v : v < me.cap})]
+    #[flux::sig(fn (self: &VecDeque<T,A>[@me], idx: usize{idx < me.cap}, addend: usize{addend <= me.cap}) -> usize{v : v < me.cap && v == ring_add(idx, addend, me.cap)})]
     fn wrap_add(&self, idx: usize, addend: usize) -> usize {
-        wrap_index(idx.wrapping_add(addend), self.cap())
+        // idx + addend < 2 * cap, so this can't overflow
+        wrap_index(idx + addend, self.cap())
     }
 
     /// Returns the index in the underlying buffer for a given logical element
     /// index - subtrahend.
     #[inline]
-    #[flux::sig(fn (self: &VecDeque<T,A>[@me], idx: usize, subtrahend: usize) -> usize{v : v < me.cap})]
+    #[flux::sig(fn (self: &VecDeque<T,A>[@me], idx: usize{idx < me.cap}, subtrahend: usize{subtrahend <= me.cap}) -> usize{v : v < me.cap && v == ring_sub(idx, subtrahend, me.cap)})]
     fn wrap_sub(&self, idx: usize, subtrahend: usize) -> usize {
-        wrap_index(idx.wrapping_sub(subtrahend), self.cap())
+        // add cap first so the difference stays non-negative and below 2 * cap
+        wrap_index(idx + self.cap() - subtrahend, self.cap())
     }
 
     /// Copies a contiguous block of memory len long from src to dst
@@ -164,6 +228,97 @@ impl<T, A: Allocator> VecDeque<T, A> {
         }
     }
 
+    /// Copies a contiguous block of memory len long from src to dst, which
+    /// may overlap
+    #[inline]
+    #[flux::sig(fn (self: &VecDeque<T,A>[@me], dst: usize{dst + len <= me.cap}, src: usize{src + len <= me.cap}, len: usize))]
+    unsafe fn copy(&self, dst: usize, src: usize, len: usize) {
+        unsafe {
+            ptr::copy(self.ptr().add(src), self.ptr().add(dst), len);
+        }
+    }
+
+    /// Rotates the whole buffer, initialized or not, `mid` slots to the left.
+    #[inline]
+    #[flux::trusted] // slices
+    #[flux::sig(fn (self: &mut VecDeque<T,A>[@me], mid: usize{mid < me.cap}))]
+    unsafe fn buffer_rotate_left(&mut self, mid: usize) {
+        let cap = self.cap();
+        unsafe {
+            slice::from_raw_parts_mut(self.ptr() as *mut MaybeUninit<T>, cap).rotate_left(mid);
+        }
+    }
+
+    /// Borrows two ranges of the buffer as slices.
+    #[inline]
+    #[flux::trusted] // slices
+    #[flux::sig(fn (&VecDeque<T,A>[@me], a: usize, a_len: usize{a + a_len <= me.cap}, b: usize, b_len: usize{b + b_len <= me.cap}) -> (&[T], &[T]))]
+    unsafe fn buffer_slices(&self, a: usize, a_len: usize, b: usize, b_len: usize) -> (&[T], &[T]) {
+        let ptr = self.ptr();
+        unsafe {
+            (
+                slice::from_raw_parts(ptr.add(a), a_len),
+                slice::from_raw_parts(ptr.add(b), b_len),
+            )
+        }
+    }
+
+    /// Mutably borrows two disjoint ranges of the buffer as slices.
+    #[inline]
+    #[flux::trusted] // slices
+    #[flux::sig(fn (&mut VecDeque<T,A>[@me], a: usize, a_len: usize{a + a_len <= me.cap}, b: usize, b_len: usize{b + b_len <= me.cap}) -> (&mut [T], &mut [T]))]
+    unsafe fn buffer_slices_mut(
+        &mut self,
+        a: usize,
+        a_len: usize,
+        b: usize,
+        b_len: usize,
+    ) -> (&mut [T], &mut [T]) {
+        let ptr = self.ptr();
+        unsafe {
+            (
+                slice::from_raw_parts_mut(ptr.add(a), a_len),
+                slice::from_raw_parts_mut(ptr.add(b), b_len),
+            )
+        }
+    }
+
+    /// Returns `true` if the contents do not wrap around the end of the buffer.
+    #[inline]
+    #[flux::sig(fn (&VecDeque<T,A>[@me]) -> bool[me.tail <= me.head])]
+    fn is_contiguous(&self) -> bool {
+        self.tail <= self.head
+    }
+
+    /// Closes the gap left by a `Drain`: the deque currently ends where the
+    /// drained range began, and `after_len` more elements start at ring
+    /// index `after_tail`. Moves whichever side is shorter.
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], after_tail: usize, after_len: usize) ensures self: VecDeque<T,A>{v: v.cap == me.cap})]
+    unsafe fn rejoin(&mut self, after_tail: usize, after_len: usize) {
+        let front_len = self.len();
+        let drain_tail = self.head;
+        if front_len < after_len {
+            let mut i = 1;
+            while i <= front_len {
+                let src = self.wrap_sub(drain_tail, i);
+                let dst = self.wrap_sub(after_tail, i);
+                self.copy(dst, src, 1);
+                i += 1;
+            }
+            self.tail = self.wrap_sub(after_tail, front_len);
+            self.head = self.wrap_add(after_tail, after_len);
+        } else {
+            let mut i = 0;
+            while i < after_len {
+                let src = self.wrap_add(after_tail, i);
+                let dst = self.wrap_add(drain_tail, i);
+                self.copy(dst, src, 1);
+                i += 1;
+            }
+            self.head = self.wrap_add(drain_tail, after_len);
+        }
+    }
+
     /// Frobs the head and tail sections around to handle the fact that we
     /// just reallocated. Unsafe because it trusts old_capacity.
     #[inline]
@@ -213,6 +368,54 @@ impl<T, A: Allocator> VecDeque<T, A> {
         // FLUX debug_assert!(self.cap().count_ones() == 1);
         assert(is_power_of_two(self.cap()));
     }
+
+    /// Moves the elements at or beyond `target_cap` below it, ahead of
+    /// shrinking the buffer to `target_cap`. Unsafe because it trusts the
+    /// caller to shrink the buffer right after.
+    #[inline]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@v], target_cap: usize{target_cap <= v.cap && ring_len(v.tail, v.head, v.cap) < target_cap}) ensures self: VecDeque<T,A>{w: w.head < target_cap && w.tail < target_cap && w.cap == v.cap})]
+    unsafe fn handle_capacity_decrease(&mut self, target_cap: usize) {
+        let cap = self.cap();
+        let tail = self.tail;
+        let head = self.head;
+
+        // Move whatever lies past the new end of the ring buffer
+        //                    T             H
+        //   [. . . . . . . . o o o o o o o . ]
+        //    T             H
+        // A [o o o o o o o . ]
+        //          T             H
+        //   [. . . o o o o o o o . . . . . . ]
+        //              H T
+        // B [o o . o o o o o ]
+        //              H                 T
+        //   [o o o o o . . . . . . . . . o o ]
+        //              H T
+        // C [o o o o o . o o ]
+
+        if tail <= head {
+            if head < target_cap {
+                // Nop
+            } else if target_cap <= tail {
+                // A
+                let len = head - tail;
+                self.copy_nonoverlapping(0, tail, len);
+                self.tail = 0;
+                self.head = len;
+            } else {
+                // B
+                let len = head - target_cap;
+                self.copy_nonoverlapping(0, target_cap, len);
+                self.head = len;
+            }
+        } else if target_cap <= tail {
+            // C
+            let len = cap - tail;
+            let new_tail = target_cap - len;
+            self.copy_nonoverlapping(new_tail, tail, len);
+            self.tail = new_tail;
+        }
+    }
 }
 
 impl<T> VecDeque<T> {
@@ -305,11 +508,10 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// assert_eq!(buf.get(1), Some(&4));
     /// ```
     //#[stable(feature = "rust1", since = "1.0.0")]
-    #[flux::trusted] // ptr
     pub fn get(&self, index: usize) -> Option<&T> {
         if index < self.len() {
             let idx = self.wrap_add(self.tail, index);
-            unsafe { Some(&*self.ptr().add(idx)) }
+            unsafe { Some(self.buffer_ref(idx)) }
         } else {
             None
         }
@@ -335,11 +537,10 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// assert_eq!(buf[1], 7);
     /// ```
     //#[stable(feature = "rust1", since = "1.0.0")]
-    #[flux::trusted]
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
         if index < self.len() {
             let idx = self.wrap_add(self.tail, index);
-            unsafe { Some(&mut *self.ptr().add(idx)) }
+            unsafe { Some(self.buffer_mut(idx)) }
         } else {
             None
         }
@@ -358,6 +559,7 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// ```
     #[inline]
     //#[stable(feature = "rust1", since = "1.0.0")]
+    #[flux::sig(fn (&VecDeque<T,A>[@me]) -> usize[me.cap - 1])]
     pub fn capacity(&self) -> usize {
         self.cap() - 1
     }
@@ -397,6 +599,252 @@ impl<T, A: Allocator> VecDeque<T, A> {
         }
     }
 
+    /// Shrinks the capacity of the deque as much as possible.
+    ///
+    /// It will drop down as close as possible to the length but the allocator may still inform the
+    /// deque that there is space for a few more elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::with_capacity(15);
+    /// buf.extend(0..4);
+    /// assert_eq!(buf.capacity(), 15);
+    /// buf.shrink_to_fit();
+    /// assert!(buf.capacity() >= 4);
+    /// ```
+    //#[stable(feature = "deque_extras_15", since = "1.5.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me]) ensures self: VecDeque<T,A>)]
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+
+    /// Shrinks the capacity of the deque with a lower bound.
+    ///
+    /// The capacity will remain at least as large as both the length
+    /// and the supplied value.
+    ///
+    /// If the current capacity is less than the lower limit, this is a no-op.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::with_capacity(15);
+    /// buf.extend(0..4);
+    /// assert_eq!(buf.capacity(), 15);
+    /// buf.shrink_to(6);
+    /// assert!(buf.capacity() >= 6);
+    /// buf.shrink_to(0);
+    /// assert!(buf.capacity() >= 4);
+    /// ```
+    //#[stable(feature = "shrink_to", since = "1.56.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], min_capacity: usize) ensures self: VecDeque<T,A>)]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let cap = self.cap();
+        let len = self.len();
+        let min_capacity = if min_capacity <= len {
+            len
+        } else if min_capacity < cap {
+            min_capacity
+        } else {
+            cap - 1
+        };
+        // +1 since the ringbuffer always leaves one space empty
+        let target_cap = real_capacity(min_capacity);
+
+        if target_cap < cap {
+            unsafe {
+                self.handle_capacity_decrease(target_cap);
+            }
+            self.buf.shrink_to_fit(target_cap);
+        }
+    }
+
+    /// Shortens the deque, keeping the first `len` elements and dropping
+    /// the rest.
+    ///
+    /// If `len` is greater than the deque's current length, this has no
+    /// effect.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.push_back(5);
+    /// buf.push_back(10);
+    /// buf.push_back(15);
+    /// assert_eq!(buf, [5, 10, 15]);
+    /// buf.truncate(1);
+    /// assert_eq!(buf, [5]);
+    /// ```
+    //#[stable(feature = "deque_extras", since = "1.16.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], len: usize) ensures self: VecDeque<T,A>)]
+    pub fn truncate(&mut self, len: usize) {
+        if self.len() <= len {
+            return;
+        }
+
+        // Cut the elements off before running any destructor, so that a
+        // panicking one cannot leave dropped elements in the deque.
+        let head = self.head;
+        let new_head = self.wrap_add(self.tail, len);
+        self.head = new_head;
+        unsafe {
+            let (front, back) = if new_head <= head {
+                self.buffer_slices_mut(new_head, head - new_head, 0, 0)
+            } else {
+                let cap = self.cap();
+                self.buffer_slices_mut(new_head, cap - new_head, 0, head)
+            };
+            drop_slices(front, back);
+        }
+    }
+
+    /// Returns a front-to-back iterator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.push_back(5);
+    /// buf.push_back(3);
+    /// buf.push_back(4);
+    /// let b: &[_] = &[&5, &3, &4];
+    /// let c: Vec<&i32> = buf.iter().collect();
+    /// assert_eq!(&c[..], b);
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            ring: self.ptr(),
+            cursor: Cursor {
+                tail: self.tail,
+                head: self.head,
+                cap: self.cap(),
+            },
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a front-to-back iterator that returns mutable references.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.push_back(5);
+    /// buf.push_back(3);
+    /// buf.push_back(4);
+    /// for num in buf.iter_mut() {
+    ///     *num = *num - 2;
+    /// }
+    /// let b: &[_] = &[&mut 3, &mut 1, &mut 2];
+    /// assert_eq!(&buf.iter_mut().collect::<Vec<&mut i32>>()[..], b);
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            ring: self.ptr(),
+            cursor: Cursor {
+                tail: self.tail,
+                head: self.head,
+                cap: self.cap(),
+            },
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns a pair of slices which contain, in order, the contents of the
+    /// deque.
+    ///
+    /// If [`make_contiguous`] was previously called, all elements of the
+    /// deque will be in the first slice and the second slice will be empty.
+    ///
+    /// [`make_contiguous`]: VecDeque::make_contiguous
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut deque = VecDeque::new();
+    ///
+    /// deque.push_back(0);
+    /// deque.push_back(1);
+    /// deque.push_back(2);
+    ///
+    /// assert_eq!(deque.as_slices(), (&[0, 1, 2][..], &[][..]));
+    ///
+    /// deque.push_front(10);
+    /// deque.push_front(9);
+    ///
+    /// assert_eq!(deque.as_slices(), (&[9, 10][..], &[0, 1, 2][..]));
+    /// ```
+    #[inline]
+    //#[stable(feature = "deque_extras_15", since = "1.5.0")]
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let tail = self.tail;
+        let head = self.head;
+        unsafe {
+            if self.is_contiguous() {
+                self.buffer_slices(tail, head - tail, 0, 0)
+            } else {
+                let cap = self.cap();
+                self.buffer_slices(tail, cap - tail, 0, head)
+            }
+        }
+    }
+
+    /// Returns a pair of slices which contain, in order, the contents of the
+    /// deque.
+    ///
+    /// If [`make_contiguous`] was previously called, all elements of the
+    /// deque will be in the first slice and the second slice will be empty.
+    ///
+    /// [`make_contiguous`]: VecDeque::make_contiguous
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut deque = VecDeque::new();
+    ///
+    /// deque.push_back(0);
+    /// deque.push_back(1);
+    ///
+    /// deque.push_front(10);
+    /// deque.push_front(9);
+    ///
+    /// deque.as_mut_slices().0[0] = 42;
+    /// deque.as_mut_slices().1[0] = 24;
+    /// assert_eq!(deque.as_slices(), (&[42, 10][..], &[24, 1][..]));
+    /// ```
+    #[inline]
+    //#[stable(feature = "deque_extras_15", since = "1.5.0")]
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let tail = self.tail;
+        let head = self.head;
+        unsafe {
+            if self.is_contiguous() {
+                self.buffer_slices_mut(tail, head - tail, 0, 0)
+            } else {
+                let cap = self.cap();
+                self.buffer_slices_mut(tail, cap - tail, 0, head)
+            }
+        }
+    }
+
     /// Returns the number of elements in the deque.
     ///
     /// # Examples
@@ -410,7 +858,7 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// assert_eq!(deque.len(), 1);
     /// ```
     //#[stable(feature = "rust1", since = "1.0.0")]
-    #[flux::sig(fn (&VecDeque<T,A>[@self]) -> usize{v: v < self.cap})]
+    #[flux::sig(fn (&VecDeque<T,A>[@self]) -> usize{v: v < self.cap && v == ring_len(self.tail, self.head, self.cap)})]
     pub fn len(&self) -> usize {
         count(self.tail, self.head, self.cap())
     }
@@ -428,10 +876,95 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// assert!(!deque.is_empty());
     /// ```
     //#[stable(feature = "rust1", since = "1.0.0")]
+    #[flux::sig(fn (&VecDeque<T,A>[@me]) -> bool[me.tail == me.head])]
     pub fn is_empty(&self) -> bool {
         self.tail == self.head
     }
 
+    /// Removes the specified range from the deque in bulk, returning all
+    /// removed elements as an iterator. If the iterator is dropped before
+    /// being fully consumed, it drops the remaining removed elements.
+    ///
+    /// The returned iterator keeps a mutable borrow on the queue to optimize
+    /// its implementation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point is greater than the end point or if
+    /// the end point is greater than the length of the deque.
+    ///
+    /// # Leaking
+    ///
+    /// If the returned iterator goes out of scope without being dropped (due to
+    /// [`mem::forget`], for example), the deque may have lost and leaked
+    /// elements arbitrarily, including elements outside the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut deque: VecDeque<_> = [1, 2, 3].into();
+    /// let drained = deque.drain(2..).collect::<VecDeque<_>>();
+    /// assert_eq!(drained, [3]);
+    /// assert_eq!(deque, [1, 2]);
+    ///
+    /// // A full range clears all contents, like `clear()` does
+    /// deque.drain(..);
+    /// assert!(deque.is_empty());
+    /// ```
+    #[inline]
+    //#[stable(feature = "drain", since = "1.6.0")]
+    pub fn drain<R>(&mut self, range: R) -> Drain<'_, T, A>
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.len();
+        let Range { start, end } = slice::range(range, ..len);
+        let tail = self.tail;
+        let drain_tail = self.wrap_add(tail, start);
+        let drain_head = self.wrap_add(tail, end);
+        let cap = self.cap();
+
+        // Only the elements before the range stay in the deque while it is
+        // drained, so a leaked `Drain` leaks the rest instead of leaving
+        // moved-out elements behind.
+        self.head = drain_tail;
+
+        Drain {
+            cursor: Cursor {
+                tail: drain_tail,
+                head: drain_head,
+                cap,
+            },
+            after_tail: drain_head,
+            after_len: len - end,
+            deque: self,
+        }
+    }
+
+    /// Clears the deque, removing all values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut deque = VecDeque::new();
+    /// deque.push_back(1);
+    /// deque.clear();
+    /// assert!(deque.is_empty());
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    #[inline]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me]) ensures self: VecDeque<T,A>)]
+    pub fn clear(&mut self) {
+        self.truncate(0);
+        // Not strictly necessary, but leaves things in a more consistent/predictable state.
+        self.head = 0;
+        self.tail = 0;
+    }
+
     /// Provides a reference to the front element, or `None` if the deque is
     /// empty.
     ///
@@ -496,7 +1029,8 @@ impl<T, A: Allocator> VecDeque<T, A> {
         self.get(self.len().wrapping_sub(1))
     }
 
-    /// Prepends an element to the deque.
+    /// Removes the first element and returns it, or `None` if the deque is
+    /// empty.
     ///
     /// # Examples
     ///
@@ -504,26 +1038,27 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// use std::collections::VecDeque;
     ///
     /// let mut d = VecDeque::new();
-    /// d.push_front(1);
-    /// d.push_front(2);
-    /// assert_eq!(d.front(), Some(&2));
+    /// d.push_back(1);
+    /// d.push_back(2);
+    ///
+    /// assert_eq!(d.pop_front(), Some(1));
+    /// assert_eq!(d.pop_front(), Some(2));
+    /// assert_eq!(d.pop_front(), None);
     /// ```
     //#[stable(feature = "rust1", since = "1.0.0")]
-    #[flux::sig(fn (self: &strg VecDeque<T,A>[@dummy], value: T) ensures self: VecDeque<T, A>)]
-    pub fn push_front(&mut self, value: T) {
-        if self.is_full() {
-            self.grow();
-        }
-
-        let tail = self.tail;
-        self.tail = self.wrap_sub(tail, 1);
-        let tail = self.tail;
-        unsafe {
-            self.buffer_write(tail, value);
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me]) -> Option<T> ensures self: VecDeque<T,A>{v: v.cap == me.cap && v.head == me.head && (me.tail == me.head => v.tail == me.tail) && (me.tail != me.head => v.tail == ring_add(me.tail, 1, me.cap))})]
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            let tail = self.tail;
+            self.tail = self.wrap_add(tail, 1);
+            unsafe { Some(self.buffer_read(tail)) }
         }
     }
 
-    /// Appends an element to the back of the deque.
+    /// Removes the last element from the deque and returns it, or `None` if
+    /// it is empty.
     ///
     /// # Examples
     ///
@@ -531,14 +1066,67 @@ impl<T, A: Allocator> VecDeque<T, A> {
     /// use std::collections::VecDeque;
     ///
     /// let mut buf = VecDeque::new();
+    /// assert_eq!(buf.pop_back(), None);
     /// buf.push_back(1);
     /// buf.push_back(3);
-    /// assert_eq!(3, *buf.back().unwrap());
+    /// assert_eq!(buf.pop_back(), Some(3));
     /// ```
     //#[stable(feature = "rust1", since = "1.0.0")]
-    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], value: T) ensures self: VecDeque<T,A>)]
-    pub fn push_back(&mut self, value: T) {
-        if self.is_full() {
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me]) -> Option<T> ensures self: VecDeque<T,A>{v: v.cap == me.cap && v.tail == me.tail && (me.tail == me.head => v.head == me.head) && (me.tail != me.head => v.head == ring_sub(me.head, 1, me.cap))})]
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            let head = self.head;
+            self.head = self.wrap_sub(head, 1);
+            let head = self.head;
+            unsafe { Some(self.buffer_read(head)) }
+        }
+    }
+
+    /// Prepends an element to the deque.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut d = VecDeque::new();
+    /// d.push_front(1);
+    /// d.push_front(2);
+    /// assert_eq!(d.front(), Some(&2));
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@dummy], value: T) ensures self: VecDeque<T, A>)]
+    pub fn push_front(&mut self, value: T) {
+        if self.is_full() {
+            self.grow();
+        }
+
+        let tail = self.tail;
+        self.tail = self.wrap_sub(tail, 1);
+        let tail = self.tail;
+        unsafe {
+            self.buffer_write(tail, value);
+        }
+    }
+
+    /// Appends an element to the back of the deque.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.push_back(1);
+    /// buf.push_back(3);
+    /// assert_eq!(3, *buf.back().unwrap());
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], value: T) ensures self: VecDeque<T,A>)]
+    pub fn push_back(&mut self, value: T) {
+        if self.is_full() {
             self.grow();
         }
 
@@ -547,6 +1135,596 @@ impl<T, A: Allocator> VecDeque<T, A> {
         unsafe { self.buffer_write(head, value) }
     }
 
+    /// Removes an element from anywhere in the deque and returns it,
+    /// replacing it with the first element.
+    ///
+    /// This does not preserve ordering, but is *O*(1).
+    ///
+    /// The flux signature requires `index < len`; unchecked callers still get
+    /// `None` if `index` is out of bounds.
+    ///
+    /// Element at index 0 is the front of the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.push_back(1);
+    /// buf.push_back(2);
+    /// buf.push_back(3);
+    /// assert_eq!(buf, [1, 2, 3]);
+    ///
+    /// assert_eq!(buf.swap_remove_front(2), Some(3));
+    /// assert_eq!(buf, [2, 1]);
+    /// ```
+    //#[stable(feature = "deque_extras_15", since = "1.5.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], index: usize{index < ring_len(me.tail, me.head, me.cap)}) -> Option<T> ensures self: VecDeque<T,A>)]
+    pub fn swap_remove_front(&mut self, index: usize) -> Option<T> {
+        if self.len() <= index {
+            return None;
+        }
+        let tail = self.tail;
+        let idx = self.wrap_add(tail, index);
+        let elem = unsafe { self.buffer_read(idx) };
+        unsafe {
+            self.copy(idx, tail, 1);
+        }
+        self.tail = self.wrap_add(tail, 1);
+        Some(elem)
+    }
+
+    /// Removes an element from anywhere in the deque and returns it,
+    /// replacing it with the last element.
+    ///
+    /// This does not preserve ordering, but is *O*(1).
+    ///
+    /// The flux signature requires `index < len`; unchecked callers still get
+    /// `None` if `index` is out of bounds.
+    ///
+    /// Element at index 0 is the front of the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.push_back(1);
+    /// buf.push_back(2);
+    /// buf.push_back(3);
+    /// assert_eq!(buf, [1, 2, 3]);
+    ///
+    /// assert_eq!(buf.swap_remove_back(0), Some(1));
+    /// assert_eq!(buf, [3, 2]);
+    /// ```
+    //#[stable(feature = "deque_extras_15", since = "1.5.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], index: usize{index < ring_len(me.tail, me.head, me.cap)}) -> Option<T> ensures self: VecDeque<T,A>)]
+    pub fn swap_remove_back(&mut self, index: usize) -> Option<T> {
+        if self.len() <= index {
+            return None;
+        }
+        let idx = self.wrap_add(self.tail, index);
+        let elem = unsafe { self.buffer_read(idx) };
+        let head = self.head;
+        self.head = self.wrap_sub(head, 1);
+        let head = self.head;
+        unsafe {
+            self.copy(idx, head, 1);
+        }
+        Some(elem)
+    }
+
+    /// Inserts an element at `index` within the deque, shifting all elements
+    /// with indices greater than or equal to `index` towards the back.
+    ///
+    /// Element at index 0 is the front of the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than deque's length
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut vec_deque = VecDeque::new();
+    /// vec_deque.push_back('a');
+    /// vec_deque.push_back('b');
+    /// vec_deque.push_back('c');
+    /// assert_eq!(vec_deque, &['a', 'b', 'c']);
+    ///
+    /// vec_deque.insert(1, 'd');
+    /// assert_eq!(vec_deque, &['a', 'd', 'b', 'c']);
+    /// ```
+    //#[stable(feature = "deque_extras_15", since = "1.5.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], index: usize{index <= ring_len(me.tail, me.head, me.cap)}, value: T) ensures self: VecDeque<T,A>)]
+    pub fn insert(&mut self, index: usize, value: T) {
+        assert!(index <= self.len(), "index out of bounds");
+        if self.is_full() {
+            self.grow();
+        }
+
+        // Move whichever side of `index` is shorter out of the way, one slot
+        // at a time, starting next to the free slot it moves into.
+        let distance_to_tail = index;
+        let distance_to_head = self.len() - index;
+        if distance_to_tail < distance_to_head {
+            let tail = self.tail;
+            self.tail = self.wrap_sub(tail, 1);
+            let mut i = 0;
+            while i < index {
+                let src = self.wrap_add(tail, i);
+                let dst = self.wrap_sub(src, 1);
+                unsafe {
+                    self.copy(dst, src, 1);
+                }
+                i += 1;
+            }
+        } else {
+            let head = self.head;
+            self.head = self.wrap_add(head, 1);
+            let mut i = 0;
+            while i < distance_to_head {
+                let dst = self.wrap_sub(head, i);
+                let src = self.wrap_sub(dst, 1);
+                unsafe {
+                    self.copy(dst, src, 1);
+                }
+                i += 1;
+            }
+        }
+
+        let idx = self.wrap_add(self.tail, index);
+        unsafe {
+            self.buffer_write(idx, value);
+        }
+    }
+
+    /// Removes and returns the element at `index` from the deque.
+    /// Whichever end is closer to the removal point will be moved to make
+    /// room, and all the affected elements will be moved to new positions.
+    /// The flux signature requires `index < len`; unchecked callers still get
+    /// `None` if `index` is out of bounds.
+    ///
+    /// Element at index 0 is the front of the queue.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.push_back(1);
+    /// buf.push_back(2);
+    /// buf.push_back(3);
+    /// assert_eq!(buf, [1, 2, 3]);
+    ///
+    /// assert_eq!(buf.remove(1), Some(2));
+    /// assert_eq!(buf, [1, 3]);
+    /// ```
+    //#[stable(feature = "rust1", since = "1.0.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], index: usize{index < ring_len(me.tail, me.head, me.cap)}) -> Option<T> ensures self: VecDeque<T,A>)]
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        let len = self.len();
+        if len <= index {
+            return None;
+        }
+
+        let idx = self.wrap_add(self.tail, index);
+        let elem = unsafe { self.buffer_read(idx) };
+
+        // Close the gap from whichever side of it is shorter, one slot at a
+        // time, starting next to the gap.
+        let distance_to_tail = index;
+        let distance_to_head = len - index - 1;
+        if distance_to_tail < distance_to_head {
+            let mut i = 0;
+            while i < index {
+                let dst = self.wrap_sub(idx, i);
+                let src = self.wrap_sub(dst, 1);
+                unsafe {
+                    self.copy(dst, src, 1);
+                }
+                i += 1;
+            }
+            let tail = self.tail;
+            self.tail = self.wrap_add(tail, 1);
+        } else {
+            let mut i = 0;
+            while i < distance_to_head {
+                let dst = self.wrap_add(idx, i);
+                let src = self.wrap_add(dst, 1);
+                unsafe {
+                    self.copy(dst, src, 1);
+                }
+                i += 1;
+            }
+            let head = self.head;
+            self.head = self.wrap_sub(head, 1);
+        }
+
+        Some(elem)
+    }
+
+    /// Splits the deque into two at the given index.
+    ///
+    /// Returns a newly allocated `VecDeque`. `self` contains elements `[0, at)`,
+    /// and the returned deque contains elements `[at, len)`.
+    ///
+    /// Note that the capacity of `self` does not change.
+    ///
+    /// Element at index 0 is the front of the queue.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf: VecDeque<_> = [1, 2, 3].into();
+    /// let buf2 = buf.split_off(1);
+    /// assert_eq!(buf, [1]);
+    /// assert_eq!(buf2, [2, 3]);
+    /// ```
+    #[inline]
+    #[must_use = "use `.truncate()` if you don't need the other half"]
+    //#[stable(feature = "split_off", since = "1.4.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], at: usize{at <= ring_len(me.tail, me.head, me.cap)}) -> VecDeque<T,A> ensures self: VecDeque<T,A>)]
+    pub fn split_off(&mut self, at: usize) -> Self
+    where
+        A: Clone,
+    {
+        let len = self.len();
+        assert!(at <= len, "`at` out of bounds");
+
+        let mut other = VecDeque::with_capacity_in(len - at, self.buf.allocator().clone());
+        other.extend(self.drain(at..));
+        other
+    }
+
+    /// Moves all the elements of `other` into `self`, leaving `other` empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new number of elements in self overflows a `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf: VecDeque<_> = [1, 2].into();
+    /// let mut buf2: VecDeque<_> = [3, 4].into();
+    /// buf.append(&mut buf2);
+    /// assert_eq!(buf, [1, 2, 3, 4]);
+    /// assert_eq!(buf2, []);
+    /// ```
+    #[inline]
+    //#[stable(feature = "append", since = "1.4.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], other: &strg VecDeque<T,A>[@you]) ensures self: VecDeque<T,A>, other: VecDeque<T,A>)]
+    pub fn append(&mut self, other: &mut Self) {
+        self.reserve(other.len());
+        while let Some(elem) = other.pop_front() {
+            self.push_back(elem);
+        }
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all elements `e` for which `f(&e)` returns false.
+    /// This method operates in place, visiting each element exactly once in the
+    /// original order, and preserves the order of the retained elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.extend(1..5);
+    /// buf.retain(|&x| x % 2 == 0);
+    /// assert_eq!(buf, [2, 4]);
+    /// ```
+    //#[stable(feature = "vec_deque_retain", since = "1.4.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], f: F) ensures self: VecDeque<T,A>)]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.retain_mut(|elem| f(elem));
+    }
+
+    /// Retains only the elements specified by the predicate.
+    ///
+    /// In other words, remove all elements `e` for which `f(&e)` returns false.
+    /// This method operates in place, visiting each element exactly once in the
+    /// original order, and preserves the order of the retained elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::new();
+    /// buf.extend(1..5);
+    /// buf.retain_mut(|x| if *x % 2 == 0 {
+    ///     *x += 1;
+    ///     true
+    /// } else {
+    ///     false
+    /// });
+    /// assert_eq!(buf, [3, 5]);
+    /// ```
+    //#[stable(feature = "vec_retain_mut", since = "1.61.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], f: F) ensures self: VecDeque<T,A>)]
+    pub fn retain_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        let len = self.len();
+        let tail = self.tail;
+        let mut idx = 0;
+        let mut cur = 0;
+
+        // Stage 1: All values are retained.
+        while cur < len {
+            let c = self.wrap_add(tail, cur);
+            if !f(unsafe { self.buffer_mut(c) }) {
+                cur += 1;
+                break;
+            }
+            cur += 1;
+            idx += 1;
+        }
+        // Stage 2: Swap retained value into current idx.
+        while cur < len {
+            let c = self.wrap_add(tail, cur);
+            if !f(unsafe { self.buffer_mut(c) }) {
+                cur += 1;
+                continue;
+            }
+
+            let i = self.wrap_add(tail, idx);
+            unsafe {
+                self.buffer_swap(i, c);
+            }
+            cur += 1;
+            idx += 1;
+        }
+        // Stage 3: Truncate all values after idx.
+        if cur != idx {
+            self.truncate(idx);
+        }
+    }
+
+    /// Rearranges the internal storage of this deque so it is one contiguous
+    /// slice, which is then returned.
+    ///
+    /// This method does not allocate and does not change the order of the
+    /// inserted elements. As it returns a mutable slice, this can be used to
+    /// sort a deque.
+    ///
+    /// Once the internal storage is contiguous, the [`as_slices`] and
+    /// [`as_mut_slices`] methods will return the entire contents of the
+    /// deque in a single slice.
+    ///
+    /// [`as_slices`]: VecDeque::as_slices
+    /// [`as_mut_slices`]: VecDeque::as_mut_slices
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf = VecDeque::with_capacity(15);
+    ///
+    /// buf.push_back(2);
+    /// buf.push_back(1);
+    /// buf.push_front(3);
+    ///
+    /// buf.make_contiguous().sort();
+    /// assert_eq!(buf.as_slices(), (&[1, 2, 3] as &[_], &[] as &[_]));
+    /// ```
+    //#[stable(feature = "deque_make_contiguous", since = "1.48.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me]) -> &mut [T] ensures self: VecDeque<T,A>)]
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        if self.is_contiguous() {
+            return self.as_mut_slices().0;
+        }
+
+        let tail = self.tail;
+        let head = self.head;
+        let cap = self.cap();
+        let len = self.len();
+        let free = tail - head;
+        let tail_len = cap - tail;
+
+        if free >= tail_len {
+            // there is enough free space to copy the tail in one go,
+            // this means that we first shift the head backwards, and then
+            // copy the tail to the correct position.
+            //
+            // from: DEFGH....ABC
+            // to:   ABCDEFGH....
+            unsafe {
+                self.copy(tail_len, 0, head);
+                // ...DEFGH.ABC
+                self.copy_nonoverlapping(0, tail, tail_len);
+                // ABCDEFGH....
+            }
+            self.tail = 0;
+            self.head = len;
+        } else if free > head {
+            // there is enough free space to copy the head in one go,
+            // this means that we first shift the tail forwards, and then
+            // copy the head to the correct position.
+            //
+            // from: FGH....ABCDE
+            // to:   ...ABCDEFGH.
+            unsafe {
+                self.copy(head, tail, tail_len);
+                // FGHABCDE....
+                self.copy_nonoverlapping(head + tail_len, 0, head);
+                // ...ABCDEFGH
+            }
+            self.tail = head;
+            self.head = self.wrap_add(head, len);
+        } else {
+            // free is smaller than both head and tail, so neither half can
+            // be moved in one go; rotate the whole ring instead.
+            //
+            // from: EFGHI...ABCD or HIJK.ABCDEFG
+            // to:   ABCDEFGHI... or ABCDEFGHIJK.
+            unsafe {
+                self.buffer_rotate_left(tail);
+            }
+            self.tail = 0;
+            self.head = len;
+        }
+
+        self.as_mut_slices().0
+    }
+
+    /// Rotates the double-ended queue `mid` places to the left.
+    ///
+    /// Equivalently,
+    /// - Rotates item `mid` into the first position.
+    /// - Pops the first `mid` items and pushes them to the end.
+    /// - Rotates `len() - mid` places to the right.
+    ///
+    /// # Panics
+    ///
+    /// If `mid` is greater than `len()`. Note that `mid == len()`
+    /// does _not_ panic and is a no-op rotation.
+    ///
+    /// # Complexity
+    ///
+    /// Takes `*O*(min(mid, len() - mid))` time and no extra space.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf: VecDeque<_> = (0..10).collect();
+    ///
+    /// buf.rotate_left(3);
+    /// assert_eq!(buf, [3, 4, 5, 6, 7, 8, 9, 0, 1, 2]);
+    ///
+    /// for i in 1..10 {
+    ///     assert_eq!(i * 3 % 10, buf[0]);
+    ///     buf.rotate_left(3);
+    /// }
+    /// assert_eq!(buf, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    /// ```
+    //#[stable(feature = "vecdeque_rotate", since = "1.36.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], mid: usize{mid <= ring_len(me.tail, me.head, me.cap)}) ensures self: VecDeque<T,A>)]
+    pub fn rotate_left(&mut self, mid: usize) {
+        assert!(mid <= self.len());
+        let k = self.len() - mid;
+        if mid <= k {
+            unsafe { self.rotate_left_inner(mid) }
+        } else {
+            unsafe { self.rotate_right_inner(k) }
+        }
+    }
+
+    /// Rotates the double-ended queue `k` places to the right.
+    ///
+    /// Equivalently,
+    /// - Rotates the first item into position `k`.
+    /// - Pops the last `k` items and pushes them to the front.
+    /// - Rotates `len() - k` places to the left.
+    ///
+    /// # Panics
+    ///
+    /// If `k` is greater than `len()`. Note that `k == len()`
+    /// does _not_ panic and is a no-op rotation.
+    ///
+    /// # Complexity
+    ///
+    /// Takes `*O*(min(k, len() - k))` time and no extra space.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::VecDeque;
+    ///
+    /// let mut buf: VecDeque<_> = (0..10).collect();
+    ///
+    /// buf.rotate_right(3);
+    /// assert_eq!(buf, [7, 8, 9, 0, 1, 2, 3, 4, 5, 6]);
+    ///
+    /// for i in 1..10 {
+    ///     assert_eq!(0, buf[i * 3 % 10]);
+    ///     buf.rotate_right(3);
+    /// }
+    /// assert_eq!(buf, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    /// ```
+    //#[stable(feature = "vecdeque_rotate", since = "1.36.0")]
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], k: usize{k <= ring_len(me.tail, me.head, me.cap)}) ensures self: VecDeque<T,A>)]
+    pub fn rotate_right(&mut self, k: usize) {
+        assert!(k <= self.len());
+        let mid = self.len() - k;
+        if k <= mid {
+            unsafe { self.rotate_right_inner(k) }
+        } else {
+            unsafe { self.rotate_left_inner(mid) }
+        }
+    }
+
+    // SAFETY: the following two methods require that the rotation amount
+    // be less than half the length of the deque.
+    //
+    // The elements move one at a time, in an order that never overwrites one
+    // still to be moved: the free space between head and tail is at least one
+    // slot, and at most half of the elements move.
+    //
+    // There is no shortcut that only moves head and tail. That works when
+    // every slot of the buffer holds an element, but this ring always keeps
+    // one slot free, so moving the ends alone would put the free slot between
+    // elements.
+
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], mid: usize{mid * 2 <= ring_len(me.tail, me.head, me.cap)}) ensures self: VecDeque<T,A>)]
+    unsafe fn rotate_left_inner(&mut self, mid: usize) {
+        debug_assert!(mid * 2 <= self.len());
+        let tail = self.tail;
+        let head = self.head;
+        // front to back: [tail, tail + mid) -> [head, head + mid)
+        let mut i = 0;
+        while i < mid {
+            let src = self.wrap_add(tail, i);
+            let dst = self.wrap_add(head, i);
+            self.copy(dst, src, 1);
+            i += 1;
+        }
+        self.head = self.wrap_add(head, mid);
+        self.tail = self.wrap_add(tail, mid);
+    }
+
+    #[flux::sig(fn (self: &strg VecDeque<T,A>[@me], k: usize{k * 2 <= ring_len(me.tail, me.head, me.cap)}) ensures self: VecDeque<T,A>)]
+    unsafe fn rotate_right_inner(&mut self, k: usize) {
+        debug_assert!(k * 2 <= self.len());
+        let tail = self.tail;
+        let head = self.head;
+        self.head = self.wrap_sub(head, k);
+        self.tail = self.wrap_sub(tail, k);
+        // back to front: [head - k, head) -> [tail - k, tail)
+        let mut i = 1;
+        while i <= k {
+            let src = self.wrap_sub(head, i);
+            let dst = self.wrap_sub(tail, i);
+            self.copy(dst, src, 1);
+            i += 1;
+        }
+    }
+
     // Double the buffer size. This method is inline(never), so we expect it to only
     // be called in cold paths.
     // This may panic or abort
@@ -568,10 +1746,495 @@ impl<T, A: Allocator> VecDeque<T, A> {
     }
 }
 
+// Comparisons and hashing go through `iter`, so two deques holding the same
+// elements agree whatever their capacity and head/tail positions.
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: PartialEq, A: Allocator> PartialEq for VecDeque<T, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: Eq, A: Allocator> Eq for VecDeque<T, A> {}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: PartialOrd, A: Allocator> PartialOrd for VecDeque<T, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: Ord, A: Allocator> Ord for VecDeque<T, A> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: Hash, A: Allocator> Hash for VecDeque<T, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        self.iter().for_each(|elem| elem.hash(state));
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T, A: Allocator> Index<usize> for VecDeque<T, A> {
+    type Output = T;
+
+    #[inline]
+    #[flux::sig(fn (&VecDeque<T,A>[@me], index: usize{index < ring_len(me.tail, me.head, me.cap)}) -> &T)]
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("Out of bounds access")
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T, A: Allocator> IndexMut<usize> for VecDeque<T, A> {
+    #[inline]
+    #[flux::sig(fn (&mut VecDeque<T,A>[@me], index: usize{index < ring_len(me.tail, me.head, me.cap)}) -> &mut T)]
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("Out of bounds access")
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T> FromIterator<T> for VecDeque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> VecDeque<T> {
+        let mut deq = VecDeque::new();
+        deq.extend(iter);
+        deq
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T, A: Allocator> Extend<T> for VecDeque<T, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        self.reserve(lower);
+        for elem in iter {
+            self.push_back(elem);
+        }
+    }
+}
+
+//#[stable(feature = "extend_ref", since = "1.2.0")]
+impl<'a, T: 'a + Copy, A: Allocator> Extend<&'a T> for VecDeque<T, A> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: fmt::Debug, A: Allocator> fmt::Debug for VecDeque<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+//#[stable(feature = "vecdeque_vec_conversions", since = "1.10.0")]
+impl<T, A: Allocator> From<Vec<T, A>> for VecDeque<T, A> {
+    /// Turn a [`Vec<T>`] into a [`VecDeque<T>`].
+    ///
+    /// This avoids reallocating where possible, but the conditions for that are
+    /// strict, and subject to change, and so shouldn't be relied upon unless the
+    /// `Vec<T>` came from `From<VecDeque<T>>` and hasn't been reallocated.
+    ///
+    /// The allocation is reused when its capacity is already a power of two
+    /// with at least one slot to spare.
+    #[flux::trusted] // Vec
+    fn from(mut other: Vec<T, A>) -> Self {
+        let len = other.len();
+        if mem::size_of::<T>() == 0 {
+            // There's no actual allocation for ZSTs to worry about capacity,
+            // but `VecDeque` can't handle as much length as `Vec`.
+            assert!(len < MAXIMUM_ZST_CAPACITY, "capacity overflow");
+        } else {
+            // We need to resize if the capacity is not a power of two, too small or
+            // doesn't have at least one free space. We do this while it's still in
+            // the `Vec` so the items will drop on panic.
+            let min_cap = cmp::max(MINIMUM_CAPACITY, len) + 1;
+            let cap = cmp::max(min_cap, other.capacity()).next_power_of_two();
+            if other.capacity() != cap {
+                other.reserve_exact(cap - len);
+            }
+            debug_assert!(other.capacity().is_power_of_two());
+        }
+
+        let mut other = ManuallyDrop::new(other);
+        let capacity = other.capacity();
+        unsafe {
+            let alloc = ptr::read(other.allocator());
+            let buf = RawVec::from_raw_parts_in(other.as_mut_ptr(), capacity, alloc);
+            VecDeque {
+                tail: 0,
+                head: len,
+                buf,
+            }
+        }
+    }
+}
+
+//#[stable(feature = "vecdeque_vec_conversions", since = "1.10.0")]
+impl<T, A: Allocator> From<VecDeque<T, A>> for Vec<T, A> {
+    /// Turn a [`VecDeque<T>`] into a [`Vec<T>`].
+    ///
+    /// This never needs to re-allocate, but does need to do *O*(*n*) data movement if
+    /// the circular buffer doesn't happen to be at the beginning of the allocation.
+    #[flux::trusted] // Vec
+    fn from(mut other: VecDeque<T, A>) -> Self {
+        other.make_contiguous();
+
+        let other = ManuallyDrop::new(other);
+        let len = other.len();
+        let cap = other.cap();
+        let tail = other.tail;
+        unsafe {
+            if tail != 0 {
+                other.copy(0, tail, len);
+            }
+            let alloc = ptr::read(other.buf.allocator());
+            Vec::from_raw_parts_in(other.ptr(), len, cap, alloc)
+        }
+    }
+}
+
+/// The pair of ring indices an iterator still has to visit, `tail` inclusive
+/// and `head` exclusive, wrapping at `cap` like the deque they came from.
+#[flux::refined_by(tail:int, head:int, cap:int)]
+#[derive(Clone, Copy)]
+struct Cursor {
+    #[flux::field({ usize[@tail] : tail < cap })]
+    tail: usize,
+    #[flux::field({ usize[@head] : head < cap })]
+    head: usize,
+    #[flux::field({ usize[@cap] : pow2(cap) && 1 <= cap })]
+    cap: usize,
+}
+
+impl Cursor {
+    /// Number of indices left to visit
+    #[flux::sig(fn (&Cursor[@me]) -> usize{v: v < me.cap && v == ring_len(me.tail, me.head, me.cap)})]
+    fn len(&self) -> usize {
+        count(self.tail, self.head, self.cap)
+    }
+
+    /// Steps the front of the cursor, returning the index it stepped over.
+    #[flux::sig(fn (self: &strg Cursor[@me]) -> Option<usize{v: v < me.cap}> ensures self: Cursor{c: c.cap == me.cap})]
+    fn next_index(&mut self) -> Option<usize> {
+        if self.tail == self.head {
+            return None;
+        }
+        let tail = self.tail;
+        self.tail = wrap_index(tail.wrapping_add(1), self.cap);
+        Some(tail)
+    }
+
+    /// Steps the back of the cursor, returning the index it stepped onto.
+    #[flux::sig(fn (self: &strg Cursor[@me]) -> Option<usize{v: v < me.cap}> ensures self: Cursor{c: c.cap == me.cap})]
+    fn next_back_index(&mut self) -> Option<usize> {
+        if self.tail == self.head {
+            return None;
+        }
+        let head = wrap_index(self.head.wrapping_sub(1), self.cap);
+        self.head = head;
+        Some(head)
+    }
+}
+
+/// An iterator over the elements of a `VecDeque`.
+///
+/// This `struct` is created by the [`iter`] method on [`VecDeque`]. See its
+/// documentation for more.
+///
+/// [`iter`]: VecDeque::iter
+//#[stable(feature = "rust1", since = "1.0.0")]
+#[flux::refined_by(cap:int)]
+pub struct Iter<'a, T: 'a> {
+    ring: *const T,
+    #[flux::field(Cursor{c: c.cap == cap})]
+    cursor: Cursor,
+    marker: PhantomData<&'a T>,
+}
+
+// SAFETY: `Iter` only hands out shared references, like `&[T]`.
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Iter {
+            ring: self.ring,
+            cursor: self.cursor,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iter<'a, T> {
+    #[inline]
+    /// The element at ring index `idx`; `ring` points at `cap` slots.
+    #[flux::trusted] // ptr
+    #[flux::sig(fn (&Iter<T>[@cap], idx: usize{idx < cap}) -> &T)]
+    unsafe fn elem(&self, idx: usize) -> &'a T {
+        unsafe { &*self.ring.add(idx) }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        let idx = self.cursor.next_index()?;
+        unsafe { Some(self.elem(idx)) }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.cursor.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a T> {
+        let idx = self.cursor.next_back_index()?;
+        unsafe { Some(self.elem(idx)) }
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+/// A mutable iterator over the elements of a `VecDeque`.
+///
+/// This `struct` is created by the [`iter_mut`] method on [`VecDeque`]. See its
+/// documentation for more.
+///
+/// [`iter_mut`]: VecDeque::iter_mut
+//#[stable(feature = "rust1", since = "1.0.0")]
+#[flux::refined_by(cap:int)]
+pub struct IterMut<'a, T: 'a> {
+    ring: *mut T,
+    #[flux::field(Cursor{c: c.cap == cap})]
+    cursor: Cursor,
+    marker: PhantomData<&'a mut T>,
+}
+
+// SAFETY: `IterMut` hands out each element at most once, like `&mut [T]`.
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> IterMut<'a, T> {
+    #[inline]
+    /// The element at ring index `idx`; `ring` points at `cap` slots.
+    #[flux::trusted] // ptr
+    #[flux::sig(fn (&mut IterMut<T>[@cap], idx: usize{idx < cap}) -> &mut T)]
+    unsafe fn elem(&mut self, idx: usize) -> &'a mut T {
+        unsafe { &mut *self.ring.add(idx) }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut T> {
+        let idx = self.cursor.next_index()?;
+        unsafe { Some(self.elem(idx)) }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.cursor.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        let idx = self.cursor.next_back_index()?;
+        unsafe { Some(self.elem(idx)) }
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+/// An owning iterator over the elements of a `VecDeque`.
+///
+/// This `struct` is created by the [`into_iter`] method on [`VecDeque`]
+/// (provided by the [`IntoIterator`] trait). See its documentation for more.
+///
+/// [`into_iter`]: VecDeque::into_iter
+//#[stable(feature = "rust1", since = "1.0.0")]
+pub struct IntoIter<T, A: Allocator = Global> {
+    inner: VecDeque<T, A>,
+}
+
+impl<T, A: Allocator> Iterator for IntoIter<T, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.inner.pop_front()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.inner.len();
+        (len, Some(len))
+    }
+}
+
+impl<T, A: Allocator> DoubleEndedIterator for IntoIter<T, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        self.inner.pop_back()
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> FusedIterator for IntoIter<T, A> {}
+
+/// A draining iterator over the elements of a `VecDeque`.
+///
+/// This `struct` is created by the [`drain`] method on [`VecDeque`]. See its
+/// documentation for more.
+///
+/// [`drain`]: VecDeque::drain
+//#[stable(feature = "drain", since = "1.6.0")]
+#[flux::refined_by(cap:int)]
+pub struct Drain<'a, T: 'a, A: Allocator = Global> {
+    #[flux::field(&mut VecDeque<T, A>{v: v.cap == cap})]
+    deque: &'a mut VecDeque<T, A>,
+    // ring indices of the drained elements not yielded yet
+    #[flux::field(Cursor{c: c.cap == cap})]
+    cursor: Cursor,
+    // where the elements after the drained range start, and how many there are
+    after_tail: usize,
+    after_len: usize,
+}
+
+impl<T, A: Allocator> Iterator for Drain<'_, T, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        let idx = self.cursor.next_index()?;
+        unsafe { Some(self.deque.buffer_read(idx)) }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.cursor.len();
+        (len, Some(len))
+    }
+}
+
+impl<T, A: Allocator> DoubleEndedIterator for Drain<'_, T, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        let idx = self.cursor.next_back_index()?;
+        unsafe { Some(self.deque.buffer_read(idx)) }
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for Drain<'_, T, A> {}
+
+impl<T, A: Allocator> FusedIterator for Drain<'_, T, A> {}
+
+//#[stable(feature = "drain", since = "1.6.0")]
+impl<T, A: Allocator> Drop for Drain<'_, T, A> {
+    fn drop(&mut self) {
+        /// Drops what is left of the range and closes the gap when it gets
+        /// dropped (normally or during unwinding), so a panicking element
+        /// destructor doesn't leak the elements after the range.
+        struct DropGuard<'r, 'a, T, A: Allocator>(&'r mut Drain<'a, T, A>);
+
+        impl<T, A: Allocator> Drop for DropGuard<'_, '_, T, A> {
+            fn drop(&mut self) {
+                self.0.for_each(drop);
+                unsafe {
+                    self.0.deque.rejoin(self.0.after_tail, self.0.after_len);
+                }
+            }
+        }
+
+        let guard = DropGuard(self);
+        guard.0.for_each(drop);
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<T, A: Allocator> IntoIterator for VecDeque<T, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+
+    /// Consumes the deque into a front-to-back iterator yielding elements by
+    /// value.
+    fn into_iter(self) -> IntoIter<T, A> {
+        IntoIter { inner: self }
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<'a, T, A: Allocator> IntoIterator for &'a VecDeque<T, A> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+//#[stable(feature = "rust1", since = "1.0.0")]
+impl<'a, T, A: Allocator> IntoIterator for &'a mut VecDeque<T, A> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+/// Drops the elements of `front` and then of `back`, still dropping `back`
+/// if a destructor in `front` panics.
+#[flux::trusted] // slices
+unsafe fn drop_slices<T>(front: &mut [T], back: &mut [T]) {
+    /// Runs the destructor for all items in the slice when it gets dropped (normally or
+    /// during unwinding).
+    struct Dropper<'a, T>(&'a mut [T]);
+
+    impl<'a, T> Drop for Dropper<'a, T> {
+        fn drop(&mut self) {
+            unsafe {
+                ptr::drop_in_place(self.0);
+            }
+        }
+    }
+
+    let _back_dropper = Dropper(back);
+    unsafe {
+        ptr::drop_in_place(front);
+    }
+}
+
 /// Returns the index in the underlying buffer for a given logical element index.
 #[inline]
 #[flux::trusted] // bitvectors
-#[flux::sig(fn (index: usize, size:Size) -> usize{v: v < size})]
+#[flux::sig(fn (index: usize, size:Size) -> usize{v: v < size && (index < size => v == index) && (size <= index && index < 2 * size => v == index - size)})]
 fn wrap_index(index: usize, size: _Size) -> usize {
     // size is always a power of 2
     assert(is_power_of_two(size));
@@ -580,11 +2243,15 @@ fn wrap_index(index: usize, size: _Size) -> usize {
 
 /// Calculate the number of elements left to be read in the buffer
 #[inline]
-#[flux::sig(fn (tail: usize, head: usize, size: Size) -> usize{v: v < size})]
+#[flux::sig(fn (tail: usize, head: usize, size: usize{size(size) && tail < size && head < size}) -> usize{v: v < size && v == ring_len(tail, head, size)})]
 fn count(tail: usize, head: usize, size: usize) -> usize {
-    // size is always a power of 2
-    // (head.wrapping_sub(tail)) & (size - 1)
-    wrap_index(head.wrapping_sub(tail), size)
+    // Same as (head.wrapping_sub(tail)) & (size - 1), split on whether the
+    // ring wraps so flux can check it without bitvectors.
+    if tail <= head {
+        head - tail
+    } else {
+        size - tail + head
+    }
 }
 
 #[flux::trusted] // exponents
@@ -604,7 +2271,7 @@ fn is_power_of_two(n: usize) -> bool {
 fn assert(_: bool) {}
 
 #[flux::trusted] // extern-spec for `max` and `next_power_of_two`
-#[flux::sig(fn (capacity: usize) -> usize{v:capacity <= v && pow2(v) && 1 <= v})]
+#[flux::sig(fn (capacity: usize) -> usize{v:capacity < v && pow2(v) && 1 <= v})]
 fn real_capacity(capacity: usize) -> usize {
     cmp::max(capacity + 1, MINIMUM_CAPACITY + 1).next_power_of_two()
 }
@@ -0,0 +1,794 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+
+use vecdeque::vec_deque::VecDeque;
+
+#[test]
+fn pop_empty() {
+    let mut d: VecDeque<i32> = VecDeque::new();
+    assert_eq!(d.pop_front(), None);
+    assert_eq!(d.pop_back(), None);
+    d.push_back(1);
+    assert_eq!(d.pop_back(), Some(1));
+    assert_eq!(d.pop_front(), None);
+    assert!(d.is_empty());
+}
+
+#[test]
+fn pop_across_wrap() {
+    // 3 usable slots in a ring of 4, so the indices wrap every few operations
+    let mut d = VecDeque::with_capacity(3);
+    assert_eq!(d.capacity(), 3);
+    for i in 0..20 {
+        d.push_back(i);
+        d.push_back(i + 100);
+        assert_eq!(d.pop_front(), Some(i));
+        assert_eq!(d.pop_front(), Some(i + 100));
+        assert_eq!(d.len(), 0);
+    }
+
+    // push_front walks the tail backwards past index 0
+    for i in 0..20 {
+        d.push_front(i);
+        d.push_back(i + 100);
+        assert_eq!(d.pop_back(), Some(i + 100));
+        assert_eq!(d.len(), 1);
+        assert_eq!(d.pop_back(), Some(i));
+    }
+    assert_eq!(d.capacity(), 3);
+}
+
+#[test]
+fn pop_after_grow() {
+    let mut d = VecDeque::with_capacity(3);
+    d.push_back(2);
+    d.push_back(3);
+    d.push_front(1);
+    assert_eq!(d.pop_front(), Some(1));
+    d.push_front(0);
+    d.push_front(-1);
+    // the ring was full and wrapped when it grew
+    d.push_front(-2);
+    assert!(d.capacity() > 3);
+    let mut out = Vec::new();
+    while let Some(x) = d.pop_front() {
+        out.push(x);
+        if let Some(y) = d.pop_back() {
+            out.push(y);
+        }
+    }
+    assert_eq!(out, [-2, 3, -1, 2, 0]);
+}
+
+/// A deque of capacity 7 whose contents `0..5` straddle the end of the ring.
+fn wrapped() -> VecDeque<i32> {
+    let mut d = VecDeque::new();
+    for i in 0..6 {
+        d.push_back(i);
+    }
+    for _ in 0..6 {
+        d.pop_front();
+    }
+    for i in 0..5 {
+        d.push_back(i);
+    }
+    assert_eq!(d.capacity(), 7);
+    d
+}
+
+#[test]
+fn iter_wrapped() {
+    let d = wrapped();
+    assert_eq!(d.iter().copied().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+    assert_eq!(d.iter().rev().copied().collect::<Vec<_>>(), [4, 3, 2, 1, 0]);
+
+    let mut it = d.iter();
+    assert_eq!(it.len(), 5);
+    assert_eq!(it.next(), Some(&0));
+    assert_eq!(it.next_back(), Some(&4));
+    assert_eq!(it.next_back(), Some(&3));
+    assert_eq!(it.len(), 2);
+    let rest = it.clone();
+    assert_eq!(it.next(), Some(&1));
+    assert_eq!(it.next(), Some(&2));
+    assert_eq!(it.next(), None);
+    assert_eq!(it.next_back(), None);
+    assert_eq!(rest.copied().collect::<Vec<_>>(), [1, 2]);
+
+    let empty: VecDeque<i32> = VecDeque::new();
+    assert_eq!(empty.iter().size_hint(), (0, Some(0)));
+    assert_eq!(empty.iter().next(), None);
+}
+
+#[test]
+fn iter_mut_wrapped() {
+    let mut d = wrapped();
+    for x in d.iter_mut() {
+        *x *= 10;
+    }
+    for x in (&mut d).into_iter().rev().take(2) {
+        *x += 1;
+    }
+    let mut seen = Vec::new();
+    for x in &d {
+        seen.push(*x);
+    }
+    assert_eq!(seen, [0, 10, 20, 31, 41]);
+    assert_eq!(d.iter_mut().len(), 5);
+}
+
+#[test]
+fn into_iter_wrapped() {
+    let mut it = wrapped().into_iter();
+    assert_eq!(it.len(), 5);
+    assert_eq!(it.next_back(), Some(4));
+    assert_eq!(it.next(), Some(0));
+    assert_eq!(it.collect::<Vec<_>>(), [1, 2, 3]);
+
+    let strings = {
+        let mut d = VecDeque::new();
+        d.push_front(String::from("b"));
+        d.push_front(String::from("a"));
+        d.push_back(String::from("c"));
+        d
+    };
+    assert_eq!(strings.into_iter().collect::<String>(), "abc");
+}
+
+/// A deque of capacity 7 holding `0..len` with the front at ring index `tail`.
+fn ring_at(tail: usize, len: usize) -> VecDeque<usize> {
+    let mut d = VecDeque::new();
+    for _ in 0..tail {
+        d.push_back(usize::MAX);
+        d.pop_front();
+    }
+    for i in 0..len {
+        d.push_back(i);
+    }
+    assert_eq!(d.capacity(), 7);
+    d
+}
+
+#[test]
+fn as_slices_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            let expected: Vec<usize> = (0..len).collect();
+            let mut d = ring_at(tail, len);
+            let (front, back) = d.as_slices();
+            assert_eq!(front.len(), len.min(8 - tail), "tail={tail} len={len}");
+            assert_eq!([front, back].concat(), expected);
+
+            let (front, back) = d.as_mut_slices();
+            for x in front.iter_mut().chain(back.iter_mut()) {
+                *x += 1;
+            }
+            let bumped: Vec<usize> = d.iter().copied().collect();
+            assert_eq!(bumped, (1..=len).collect::<Vec<_>>());
+        }
+    }
+}
+
+#[test]
+fn make_contiguous_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            let expected: Vec<usize> = (0..len).collect();
+            let mut d = ring_at(tail, len);
+            assert_eq!(d.make_contiguous(), &expected[..], "tail={tail} len={len}");
+            let (front, back) = d.as_slices();
+            assert_eq!((front, back), (&expected[..], &[][..]));
+            assert_eq!(d.len(), len);
+            assert_eq!(d.capacity(), 7);
+
+            // the deque is still a working ring afterwards
+            d.push_front(100);
+            if len < 7 {
+                d.push_back(200);
+            }
+            assert_eq!(d.pop_front(), Some(100));
+            if len < 7 {
+                assert_eq!(d.pop_back(), Some(200));
+            }
+            assert_eq!(d.iter().copied().collect::<Vec<_>>(), expected);
+        }
+    }
+}
+
+#[test]
+fn make_contiguous_moves_owned_values() {
+    let mut d = VecDeque::new();
+    for i in 0..5 {
+        d.push_back(i.to_string());
+    }
+    for _ in 0..4 {
+        let s = d.pop_front().unwrap();
+        d.push_back(s);
+    }
+    d.make_contiguous().sort();
+    assert_eq!(d.as_slices().0, ["0", "1", "2", "3", "4"]);
+}
+
+/// The capacity `shrink_to_fit` should settle on for `len` elements.
+fn fitted_capacity(len: usize) -> usize {
+    (len + 1).max(2).next_power_of_two() - 1
+}
+
+#[test]
+fn shrink_to_fit_every_layout() {
+    for tail in 0..16 {
+        for len in 0..16 {
+            let mut d = VecDeque::with_capacity(15);
+            for _ in 0..tail {
+                d.push_back(usize::MAX);
+                d.pop_front();
+            }
+            for i in 0..len {
+                d.push_back(i);
+            }
+            assert_eq!(d.capacity(), 15);
+
+            d.shrink_to_fit();
+            assert_eq!(d.capacity(), fitted_capacity(len), "tail={tail} len={len}");
+            let expected: Vec<usize> = (0..len).collect();
+            assert_eq!(d.iter().copied().collect::<Vec<_>>(), expected);
+
+            // the ring still wraps and grows correctly at the new size
+            d.push_front(100);
+            d.push_back(200);
+            assert_eq!(d.pop_front(), Some(100));
+            assert_eq!(d.pop_back(), Some(200));
+            assert_eq!(d.make_contiguous(), &expected[..]);
+        }
+    }
+}
+
+#[test]
+fn shrink_to_bounds() {
+    let mut d = VecDeque::with_capacity(1000);
+    for i in 0..1000 {
+        d.push_back(i);
+    }
+    for _ in 0..990 {
+        d.pop_front();
+    }
+    assert_eq!(d.capacity(), 1023);
+
+    // a lower bound above the current capacity changes nothing
+    d.shrink_to(5000);
+    assert_eq!(d.capacity(), 1023);
+    d.shrink_to(100);
+    assert_eq!(d.capacity(), 127);
+    // never below the length
+    d.shrink_to(3);
+    assert_eq!(d.capacity(), 15);
+    assert_eq!(
+        d.iter().copied().collect::<Vec<_>>(),
+        (990..1000).collect::<Vec<_>>()
+    );
+
+    let mut empty: VecDeque<String> = VecDeque::with_capacity(64);
+    empty.shrink_to_fit();
+    assert_eq!(empty.capacity(), 1);
+    empty.push_back(String::from("x"));
+    empty.push_back(String::from("y"));
+    assert_eq!(empty.pop_front().as_deref(), Some("x"));
+    assert_eq!(empty.pop_front().as_deref(), Some("y"));
+}
+
+/// Our deque and std's, both holding `0..len` with ours starting at ring
+/// index `tail` of a capacity 7 buffer.
+fn model_at(tail: usize, len: usize) -> (VecDeque<usize>, std::collections::VecDeque<usize>) {
+    (ring_at(tail, len), (0..len).collect())
+}
+
+fn contents(d: &VecDeque<usize>) -> Vec<usize> {
+    d.iter().copied().collect()
+}
+
+#[test]
+fn insert_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            for index in 0..=len {
+                let (mut d, mut model) = model_at(tail, len);
+                d.insert(index, 100);
+                model.insert(index, 100);
+                let ctx = format!("tail={tail} len={len} index={index}");
+                assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+                // a full ring grows first
+                assert_eq!(d.capacity(), if len < 7 { 7 } else { 15 }, "{ctx}");
+            }
+        }
+    }
+}
+
+#[test]
+fn remove_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            for index in 0..len {
+                let ctx = format!("tail={tail} len={len} index={index}");
+
+                let (mut d, mut model) = model_at(tail, len);
+                assert_eq!(d.remove(index), model.remove(index), "{ctx}");
+                assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+
+                let (mut d, mut model) = model_at(tail, len);
+                let front = d.swap_remove_front(index);
+                assert_eq!(front, model.swap_remove_front(index), "{ctx}");
+                assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+
+                let (mut d, mut model) = model_at(tail, len);
+                let back = d.swap_remove_back(index);
+                assert_eq!(back, model.swap_remove_back(index), "{ctx}");
+                assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+            }
+
+            let (mut d, _) = model_at(tail, len);
+            assert_eq!(d.remove(len), None);
+            assert_eq!(d.swap_remove_front(len), None);
+            assert_eq!(d.swap_remove_back(len), None);
+            assert_eq!(d.len(), len);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds")]
+fn insert_past_end() {
+    let mut d = ring_at(5, 3);
+    d.insert(4, 0);
+}
+
+/// Counts its own drops, and panics while dropping if `boom` is set.
+struct Counted<'a> {
+    drops: &'a Cell<usize>,
+    boom: bool,
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+        if self.boom {
+            panic!("boom");
+        }
+    }
+}
+
+/// A capacity 7 deque of `len` counted elements starting at ring index `tail`.
+fn counted_at(tail: usize, len: usize, drops: &Cell<usize>) -> VecDeque<Counted<'_>> {
+    let mut d = VecDeque::new();
+    for _ in 0..tail {
+        d.push_back(Counted { drops, boom: false });
+        d.pop_front();
+    }
+    drops.set(0);
+    for _ in 0..len {
+        d.push_back(Counted { drops, boom: false });
+    }
+    assert_eq!(d.capacity(), 7);
+    d
+}
+
+#[test]
+fn drop_every_layout() {
+    let drops = Cell::new(0);
+    for tail in 0..8 {
+        for len in 0..8 {
+            drop(counted_at(tail, len, &drops));
+            assert_eq!(drops.get(), len, "tail={tail} len={len}");
+        }
+    }
+}
+
+#[test]
+fn truncate_every_layout() {
+    let drops = Cell::new(0);
+    for tail in 0..8 {
+        for len in 0..8 {
+            for keep in 0..=len + 1 {
+                let ctx = format!("tail={tail} len={len} keep={keep}");
+                let mut d = counted_at(tail, len, &drops);
+                d.truncate(keep);
+                let kept = keep.min(len);
+                assert_eq!(drops.get(), len - kept, "{ctx}");
+                assert_eq!(d.len(), kept, "{ctx}");
+                drop(d);
+                assert_eq!(drops.get(), len, "{ctx}");
+            }
+        }
+    }
+}
+
+#[test]
+fn clear_and_reuse() {
+    let drops = Cell::new(0);
+    let mut d = counted_at(5, 6, &drops);
+    d.clear();
+    assert_eq!(drops.get(), 6);
+    assert!(d.is_empty());
+    d.push_front(Counted {
+        drops: &drops,
+        boom: false,
+    });
+    drop(d);
+    assert_eq!(drops.get(), 7);
+}
+
+#[test]
+fn moved_out_elements_are_not_dropped_again() {
+    let drops = Cell::new(0);
+    let mut d = counted_at(6, 7, &drops);
+    drop(d.pop_front());
+    drop(d.pop_back());
+    drop(d.remove(2));
+    drop(d.swap_remove_back(0));
+    d.make_contiguous();
+    d.shrink_to_fit();
+    assert_eq!(drops.get(), 4);
+
+    let mut it = d.into_iter();
+    drop(it.next());
+    assert_eq!(drops.get(), 5);
+    drop(it);
+    assert_eq!(drops.get(), 7);
+}
+
+#[test]
+fn panicking_destructor_still_drops_the_rest() {
+    let drops = Cell::new(0);
+    // four elements in the front segment, three wrapped into the back one
+    let mut d = counted_at(4, 7, &drops);
+    assert_eq!(d.as_slices().0.len(), 4);
+    d.get_mut(1).unwrap().boom = true;
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| drop(d)));
+    assert!(result.is_err());
+    assert_eq!(drops.get(), 7);
+}
+
+#[test]
+fn extend_reserves_once() {
+    let mut d = ring_at(5, 3);
+    d.extend(3..20);
+    assert_eq!(d.capacity(), 31);
+    assert_eq!(contents(&d), (0..20).collect::<Vec<_>>());
+    d.extend(&[20, 21]);
+    assert_eq!(contents(&d), (0..22).collect::<Vec<_>>());
+
+    // iterators without a useful size hint still work
+    let mut d = ring_at(6, 2);
+    d.extend((2..40).filter(|_| true));
+    assert_eq!(contents(&d), (0..40).collect::<Vec<_>>());
+}
+
+#[test]
+fn append_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            let mut d = ring_at(tail, len);
+            let mut other = ring_at(7 - tail, 7 - len);
+            d.append(&mut other);
+            let mut expected: Vec<usize> = (0..len).collect();
+            expected.extend(0..7 - len);
+            assert_eq!(contents(&d), expected, "tail={tail} len={len}");
+            assert!(other.is_empty());
+            assert_eq!(other.capacity(), 7);
+        }
+    }
+}
+
+#[test]
+fn drain_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            for start in 0..=len {
+                for end in start..=len {
+                    let ctx = format!("tail={tail} len={len} range={start}..{end}");
+                    let (mut d, mut model) = model_at(tail, len);
+                    let drained: Vec<usize> = d.drain(start..end).collect();
+                    let expected: Vec<usize> = model.drain(start..end).collect();
+                    assert_eq!(drained, expected, "{ctx}");
+                    assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+
+                    // the deque is whole again afterwards
+                    d.push_front(100);
+                    d.push_back(200);
+                    assert_eq!(d.len(), len - (end - start) + 2, "{ctx}");
+                    assert_eq!(d.pop_front(), Some(100));
+                    assert_eq!(d.pop_back(), Some(200));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn drain_partially_consumed() {
+    let drops = Cell::new(0);
+    let mut d = counted_at(5, 7, &drops);
+    {
+        let mut drain = d.drain(1..6);
+        assert_eq!(drain.len(), 5);
+        drop(drain.next());
+        drop(drain.next_back());
+        assert_eq!(drain.len(), 3);
+        assert_eq!(drops.get(), 2);
+    }
+    assert_eq!(drops.get(), 5);
+    assert_eq!(d.len(), 2);
+    drop(d);
+    assert_eq!(drops.get(), 7);
+
+    let mut d = ring_at(3, 6);
+    let mut drain = d.drain(2..);
+    assert_eq!(drain.next_back(), Some(5));
+    assert_eq!(drain.next(), Some(2));
+    drop(drain);
+    assert_eq!(contents(&d), [0, 1]);
+}
+
+#[test]
+fn leaked_drain_leaves_the_front() {
+    let drops = Cell::new(0);
+    let mut d = counted_at(4, 6, &drops);
+    std::mem::forget(d.drain(2..4));
+    assert_eq!(d.len(), 2);
+    drop(d);
+    assert_eq!(drops.get(), 2);
+}
+
+#[test]
+fn panicking_drain_still_rejoins() {
+    let drops = Cell::new(0);
+    let mut d = counted_at(5, 7, &drops);
+    d.get_mut(2).unwrap().boom = true;
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| drop(d.drain(1..5))));
+    assert!(result.is_err());
+    assert_eq!(drops.get(), 4);
+    assert_eq!(d.len(), 3);
+    drop(d);
+    assert_eq!(drops.get(), 7);
+}
+
+#[test]
+#[should_panic]
+fn drain_out_of_range() {
+    let mut d = ring_at(2, 3);
+    d.drain(1..4);
+}
+
+#[test]
+fn split_off_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            for at in 0..=len {
+                let ctx = format!("tail={tail} len={len} at={at}");
+                let (mut d, mut model) = model_at(tail, len);
+                let other = d.split_off(at);
+                let expected = model.split_off(at);
+                assert_eq!(contents(&other), Vec::from(expected), "{ctx}");
+                assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+                assert_eq!(d.capacity(), 7, "{ctx}");
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "`at` out of bounds")]
+fn split_off_past_end() {
+    let mut d = ring_at(6, 2);
+    let _ = d.split_off(3);
+}
+
+#[test]
+fn retain_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            for mask in 0..1u32 << len {
+                let keep = |x: &usize| mask & (1 << x) != 0;
+                let (mut d, mut model) = model_at(tail, len);
+                d.retain(keep);
+                model.retain(keep);
+                assert_eq!(contents(&d), Vec::from(model), "tail={tail} mask={mask:b}");
+            }
+        }
+    }
+
+    let drops = Cell::new(0);
+    let mut d = counted_at(3, 7, &drops);
+    let mut seen = 0;
+    d.retain_mut(|c| {
+        seen += 1;
+        c.boom = false;
+        seen % 3 == 0
+    });
+    assert_eq!(drops.get(), 5);
+    assert_eq!(d.len(), 2);
+}
+
+#[test]
+fn rotate_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            for n in 0..=len {
+                let ctx = format!("tail={tail} len={len} n={n}");
+
+                let (mut d, mut model) = model_at(tail, len);
+                d.rotate_left(n);
+                model.rotate_left(n);
+                assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+
+                let (mut d, mut model) = model_at(tail, len);
+                d.rotate_right(n);
+                model.rotate_right(n);
+                assert_eq!(contents(&d), Vec::from(model), "{ctx}");
+                assert_eq!(d.capacity(), 7, "{ctx}");
+            }
+        }
+    }
+}
+
+#[test]
+fn rotate_round_robin() {
+    let drops = Cell::new(0);
+    let mut d = counted_at(2, 7, &drops);
+    for _ in 0..20 {
+        d.rotate_left(1);
+        d.rotate_right(3);
+    }
+    assert_eq!(drops.get(), 0);
+    drop(d);
+    assert_eq!(drops.get(), 7);
+
+    let mut d = ring_at(4, 5);
+    for round in 0..10 {
+        assert_eq!(d.front(), Some(&(round % 5)));
+        d.rotate_left(1);
+    }
+}
+
+#[test]
+#[should_panic]
+fn rotate_past_len() {
+    ring_at(1, 3).rotate_right(4);
+}
+
+#[test]
+fn index_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            let mut d = ring_at(tail, len);
+            for i in 0..len {
+                assert_eq!(d[i], i);
+                assert_eq!(d.get(i), Some(&i));
+                d[i] += 10;
+                *d.get_mut(i).unwrap() += 10;
+            }
+            assert_eq!(d.get(len), None);
+            assert_eq!(d.get_mut(len), None);
+            assert_eq!(d.get(usize::MAX), None);
+            assert_eq!(contents(&d), (20..20 + len).collect::<Vec<_>>());
+            assert_eq!(d.front(), d.get(0));
+            assert_eq!(d.back(), len.checked_sub(1).and_then(|i| d.get(i)));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "Out of bounds access")]
+fn index_past_end() {
+    let d = ring_at(6, 3);
+    let _ = d[3];
+}
+
+#[test]
+#[should_panic(expected = "Out of bounds access")]
+fn index_mut_past_end() {
+    let mut d = ring_at(6, 0);
+    d[0] = 1;
+}
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn comparisons_ignore_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            let d = ring_at(tail, len);
+            let mut other: VecDeque<usize> = VecDeque::with_capacity(40);
+            other.extend(0..len);
+            assert_eq!(d, other);
+            assert_eq!(d.cmp(&other), Ordering::Equal);
+            assert_eq!(hash_of(&d), hash_of(&other));
+            assert_eq!(d.clone(), d);
+
+            other.push_back(0);
+            assert_ne!(d, other);
+            assert!(d < other);
+            other.pop_back();
+            if len > 0 {
+                other[len - 1] += 1;
+                assert!(d < other);
+                assert_eq!(other.partial_cmp(&d), Some(Ordering::Greater));
+            }
+        }
+    }
+}
+
+#[test]
+fn clone_and_debug() {
+    let d = {
+        let mut d = VecDeque::new();
+        d.push_back(String::from("b"));
+        d.push_front(String::from("a"));
+        d
+    };
+    let copy = d.clone();
+    drop(d);
+    assert_eq!(format!("{copy:?}"), r#"["a", "b"]"#);
+    assert_eq!(format!("{:?}", VecDeque::<u8>::new()), "[]");
+}
+
+#[test]
+fn collect() {
+    let d: VecDeque<usize> = (0..10).collect();
+    assert_eq!(contents(&d), (0..10).collect::<Vec<_>>());
+    let d: VecDeque<usize> = std::iter::empty().collect();
+    assert!(d.is_empty());
+}
+
+#[test]
+fn from_vec_reuses_power_of_two_allocations() {
+    let mut v = Vec::with_capacity(8);
+    v.extend(0..5);
+    let ptr = v.as_ptr();
+    let d = VecDeque::from(v);
+    assert_eq!(d.capacity(), 7);
+    assert_eq!(d.as_slices().0.as_ptr(), ptr);
+    assert_eq!(contents(&d), [0, 1, 2, 3, 4]);
+
+    // no spare slot, or not a power of two: reallocate
+    for (cap, len, expected) in [(8, 8, 15), (6, 3, 7), (0, 0, 1), (3, 1, 3)] {
+        let mut v = Vec::with_capacity(cap);
+        v.extend(0..len);
+        let d = VecDeque::from(v);
+        assert_eq!(d.capacity(), expected, "cap={cap} len={len}");
+        assert_eq!(contents(&d), (0..len).collect::<Vec<_>>());
+    }
+
+    let zsts = VecDeque::from(vec![(); 10]);
+    assert_eq!(zsts.len(), 10);
+}
+
+#[test]
+fn into_vec_every_layout() {
+    for tail in 0..8 {
+        for len in 0..8 {
+            let d = ring_at(tail, len);
+            let ptr = d.as_slices().0.as_ptr().wrapping_sub(tail);
+            let v: Vec<usize> = d.into();
+            assert_eq!(v, (0..len).collect::<Vec<_>>(), "tail={tail} len={len}");
+            assert_eq!(v.capacity(), 8);
+            assert_eq!(v.as_ptr(), ptr);
+        }
+    }
+
+    let drops = Cell::new(0);
+    let v: Vec<Counted> = counted_at(5, 6, &drops).into();
+    assert_eq!(drops.get(), 0);
+    let back = VecDeque::from(v);
+    assert_eq!(back.len(), 6);
+    drop(back);
+    assert_eq!(drops.get(), 6);
+}
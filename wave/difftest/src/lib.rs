@@ -0,0 +1,107 @@
+//! Shared setup for driving the flux and prusti copies of wave with the same
+//! generated inputs.
+
+use flux_wave::rvec::RVec;
+
+pub use flux_wave as flux;
+pub use prusti_wave as prusti;
+
+/// Small deterministic generator (xorshift64*), so a failing case can be
+/// replayed from its seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    pub fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    /// A value in `0..=max`, biased towards the ends of the range and
+    /// `pivot`, where off-by-one bugs live.
+    pub fn edgy(&mut self, max: u64, pivot: u64) -> u64 {
+        let v = match self.below(4) {
+            0 => self.below(16),
+            1 => max - self.below(16.min(max + 1)),
+            2 => pivot.saturating_sub(8) + self.below(16),
+            _ => self.next_u64() % (max.saturating_add(1)).max(1),
+        };
+        v.min(max)
+    }
+}
+
+/// Both runtimes' sandbox contexts, with identical linear memory.
+pub struct Ctxs {
+    pub flux: flux::types::VmCtx,
+    pub prusti: prusti::types::VmCtx,
+}
+
+impl Ctxs {
+    pub fn new() -> Ctxs {
+        let mem = vec![0; prusti::types::LINEAR_MEM_SIZE];
+        Ctxs {
+            flux: flux::types::VmCtx::new(RVec::new(), 0, RVec::new(), 0),
+            prusti: prusti::types::VmCtx {
+                raw: mem.as_ptr() as usize,
+                memlen: mem.len(),
+                mem,
+                arg_buffer: Vec::new(),
+                env_buffer: Vec::new(),
+                envc: 0,
+                argc: 0,
+            },
+        }
+    }
+
+    /// Write `bytes` at `addr` in both memories, dropping whatever falls
+    /// outside linear memory.
+    pub fn write(&mut self, addr: usize, bytes: &[u8]) {
+        for (i, b) in bytes.iter().enumerate() {
+            if addr + i < self.prusti.memlen {
+                self.flux.write_u8(addr + i, *b).unwrap();
+                self.prusti.mem[addr + i] = *b;
+            }
+        }
+    }
+}
+
+impl Default for Ctxs {
+    fn default() -> Self {
+        Ctxs::new()
+    }
+}
+
+pub fn flux_error(e: flux::types::RuntimeError) -> String {
+    format!("{:?}", e)
+}
+
+pub fn prusti_error(e: prusti::types::RuntimeError) -> String {
+    use prusti::types::RuntimeError::*;
+    match e {
+        Success => "Success",
+        Efault => "Efault",
+        Eoverflow => "Eoverflow",
+        Eloop => "Eloop",
+        Enotcapable => "Enotcapable",
+        Enametoolong => "Enametoolong",
+    }
+    .to_string()
+}
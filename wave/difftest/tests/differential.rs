@@ -0,0 +1,213 @@
+// the flux tests' fixtures refer to the flux crate as `wave`
+extern crate flux_wave as wave;
+
+#[path = "../../flux/tests/common/mod.rs"]
+mod common;
+
+use std::fs;
+use std::os::unix::io::AsRawFd;
+
+use common::hostile_tree;
+use wave_difftest::*;
+
+const ITERATIONS: u64 = 20_000;
+
+#[test]
+fn bounds_helpers_agree() {
+    let ctxs = Ctxs::new();
+    let (f, p) = (&ctxs.flux, &ctxs.prusti);
+    let memlen = p.memlen as u64;
+    let mut rng = Rng::new(0xb0d5);
+    for _ in 0..ITERATIONS {
+        let buf = rng.edgy(u32::MAX as u64, memlen) as u32;
+        let cnt = rng.edgy(u32::MAX as u64, memlen.saturating_sub(buf as u64)) as u32;
+        assert_eq!(f.in_lin_mem(buf), p.in_lin_mem(buf), "in_lin_mem({})", buf);
+        assert_eq!(
+            f.fits_in_lin_mem(buf, cnt),
+            p.fits_in_lin_mem(buf, cnt),
+            "fits_in_lin_mem({}, {})",
+            buf,
+            cnt
+        );
+
+        // the prusti copy adds without overflow checks, so stay well below
+        // usize::MAX
+        let buf = rng.edgy(1 << 40, memlen) as usize;
+        let cnt = rng.edgy(1 << 40, memlen.saturating_sub(buf as u64)) as usize;
+        assert_eq!(f.in_lin_mem_usize(buf), p.in_lin_mem_usize(buf));
+        assert_eq!(
+            f.fits_in_lin_mem_usize(buf, cnt),
+            p.fits_in_lin_mem_usize(buf, cnt),
+            "fits_in_lin_mem_usize({}, {})",
+            buf,
+            cnt
+        );
+    }
+}
+
+#[test]
+fn parse_and_translate_iovs_agree() {
+    let mut ctxs = Ctxs::new();
+    let memlen = ctxs.prusti.memlen as u64;
+    let mut rng = Rng::new(0x10e5);
+    for i in 0..ITERATIONS / 10 {
+        let iovcnt = rng.below(17) as u32;
+        // the prusti copy computes `iovs + i * 8` in u32
+        let max_iovs = u32::MAX as u64 - 8 * iovcnt as u64;
+        let iovs = rng.edgy(max_iovs, memlen - 8 * iovcnt as u64) as u32;
+        let mut bytes = Vec::new();
+        for _ in 0..iovcnt {
+            // mostly short in-bounds buffers, so that whole arrays are often
+            // accepted
+            let (base, len) = if rng.below(16) == 0 {
+                let base = rng.edgy(u32::MAX as u64, memlen);
+                (base, rng.edgy(u32::MAX as u64, memlen.saturating_sub(base)))
+            } else {
+                (rng.below(memlen - 4096), rng.below(4096))
+            };
+            let (base, len) = (base as u32, len as u32);
+            bytes.extend_from_slice(&base.to_le_bytes());
+            bytes.extend_from_slice(&len.to_le_bytes());
+        }
+        ctxs.write(iovs as usize, &bytes);
+
+        let f = flux::iov::parse_iovs(&ctxs.flux, iovs, iovcnt);
+        let p = prusti::iov::parse_iovs(&ctxs.prusti, iovs, iovcnt);
+
+        // The prusti copy has no memory64 layout, so check flux's against it
+        // with the same iovecs widened to u64, placed where the wider array
+        // fits. Only comparable when the wasm32 array itself was in memory.
+        let iovs64 = rng.below(memlen - 16 * iovcnt as u64);
+        let wide: Vec<u8> = bytes
+            .chunks(4)
+            .flat_map(|w| (u32::from_le_bytes(w.try_into().unwrap()) as u64).to_le_bytes())
+            .collect();
+        ctxs.write(iovs64 as usize, &wide);
+        let f64 = flux::iov::parse_iovs(&ctxs.flux, iovs64, iovcnt)
+            .map(|f| flux_iovs(&f))
+            .map_err(flux_error);
+        let wide_comparable = (iovs as u64) + 8 * (iovcnt as u64) < memlen;
+
+        let case = format!("case {}: parse_iovs({:#x}, {})", i, iovs, iovcnt);
+        match (f, p) {
+            (Ok(f), Ok(p)) => {
+                let f_iovs = flux_iovs(&f);
+                let p_iovs: Vec<(u64, u64)> = p
+                    .iovs
+                    .iter()
+                    .map(|iov| (iov.iov_base as u64, iov.iov_len as u64))
+                    .collect();
+                assert_eq!(f_iovs, p_iovs, "{}", case);
+                assert_eq!(f64, Ok(p_iovs), "{} as memory64 at {:#x}", case, iovs64);
+
+                // the memories live at different addresses, so compare
+                // offsets from each base
+                let f_native: Vec<(usize, usize)> = ctxs
+                    .flux
+                    .translate_iovs(&f)
+                    .as_slice()
+                    .iter()
+                    .map(|iov| (iov.iov_base - ctxs.flux.raw, iov.iov_len))
+                    .collect();
+                let p_native: Vec<(usize, usize)> = ctxs
+                    .prusti
+                    .translate_iovs(&p)
+                    .iovs
+                    .iter()
+                    .map(|iov| (iov.iov_base - ctxs.prusti.raw, iov.iov_len))
+                    .collect();
+                assert_eq!(f_native, p_native, "{}", case);
+            }
+            (Err(f), Err(p)) => {
+                let p = prusti_error(p);
+                assert_eq!(flux_error(f), p, "{}", case);
+                if wide_comparable {
+                    assert_eq!(f64, Err(p), "{} as memory64 at {:#x}", case, iovs64);
+                }
+            }
+            (f, p) => panic!("{}: flux ok={} prusti ok={}", case, f.is_ok(), p.is_ok()),
+        }
+    }
+}
+
+/// `(base, len)` of each parsed flux iovec.
+fn flux_iovs(iovs: &flux::rvec::RVec<flux::types::WasmIoVec>) -> Vec<(u64, u64)> {
+    iovs.as_slice()
+        .iter()
+        .map(|iov| (iov.iov_base as u64, iov.iov_len as u64))
+        .collect()
+}
+
+/// Every name in `hostile_tree`, plus a few that are not there.
+const COMPONENTS: &[&str] = &[
+    "dir",
+    "sub",
+    "file",
+    "other",
+    "up",
+    "upup",
+    "sib",
+    "back",
+    "abs",
+    "abs_file",
+    "escape",
+    "proc",
+    "loop1",
+    "loop2",
+    "self",
+    "dangling",
+    "dangling_up",
+    "chain",
+    "c0",
+    "c1",
+    "c2",
+    "c3",
+    "c4",
+    "c_out",
+    "etc",
+    "passwd",
+    "missing",
+    "..",
+    ".",
+    "",
+];
+
+/// Compares the two copies of `path_resolution.rs` only. The prusti copy's
+/// own owned-components stubs out `readlinkat`, so prusti-wave links the
+/// flux copy's instead (see prusti-wave/Cargo.toml). Both sides therefore
+/// read symlinks through the same code, and this says nothing about the
+/// prusti copy's owned-components.
+#[test]
+fn path_resolution_logic_agrees() {
+    let (root, dir) = hostile_tree();
+    let f_fd = flux::types::HostFd::from_raw(dir.as_raw_fd() as usize);
+    let p_fd = prusti::types::HostFd::from_raw(dir.as_raw_fd() as usize);
+    let mut rng = Rng::new(0x9a74);
+    for i in 0..ITERATIONS / 10 {
+        let n = rng.below(7);
+        let mut path: Vec<&str> = (0..n).map(|_| *rng.pick(COMPONENTS)).collect();
+        if rng.below(8) == 0 {
+            path.insert(0, "");
+        }
+        let path = path.join("/");
+        let should_follow = rng.coin();
+
+        let bytes = path.as_bytes().to_vec();
+        let f = flux::path_resolution::resolve_path(
+            flux::rvec::RVec::from_vec(bytes.clone()),
+            should_follow,
+            f_fd,
+        );
+        let p = prusti::path_resolution::resolve_path(bytes, should_follow, p_fd);
+        let case = format!("case {}: resolve_path({:?}, {})", i, path, should_follow);
+        match (f, p) {
+            (Ok(f), Ok(p)) => {
+                let len = p.iter().position(|b| *b == 0).unwrap_or(p.len());
+                assert_eq!(f.as_bytes(), &p[..len], "{}", case);
+            }
+            (Err(f), Err(p)) => assert_eq!(flux_error(f), prusti_error(p), "{}", case),
+            (f, p) => panic!("{}: flux ok={} prusti ok={}", case, f.is_ok(), p.is_ok()),
+        }
+    }
+    fs::remove_dir_all(root).unwrap();
+}
@@ -3,13 +3,14 @@
 // #![feature(custom_inner_attributes)]
 // #![flux::ignore] // ignore checking this crate
 
+extern crate libc;
+
 use std::path::{Component, Path, PathBuf};
 // use std::ffi::{OsStr, OsString};
 use std::ffi::{CString, OsStr, OsString};
 use std::io;
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 use std::os::unix::ffi::{OsStrExt, OsStringExt};
-//use libc;
 
 // Represents an Owned version of a Component<'a>
 // Currently only works for *nix (neglects the prefix component present on windows)
@@ -122,22 +123,18 @@ fn cstr(path: &Path) -> io::Result<CString> {
     Ok(CString::new(path.as_os_str().as_bytes())?)
 }
 
-#[allow(unused_variables)]
 // taken from https://benaaron.dev/rust-docs/src/std/sys/unix/fs.rs.html#1109-1134
 // TODO: the principled way to do this is to use our os spec
 pub fn readlinkat(fd: usize, p: &Path) -> io::Result<PathBuf> {
     let c_path = cstr(p)?;
     let p = c_path.as_ptr();
 
-    let mut buf = Vec::with_capacity(4096);
+    let mut buf: Vec<u8> = Vec::with_capacity(4096);
 
-    let buf_read: i32 = 10; // TODO-FLUX avoid libc
-                            // unsafe { libc::readlinkat(fd as i32, p, buf.as_mut_ptr() as *mut _, buf.capacity()) };
+    let buf_read =
+        unsafe { libc::readlinkat(fd as i32, p, buf.as_mut_ptr() as *mut _, buf.capacity()) };
     if buf_read == -1 {
-        return Err(Error::new(
-            ErrorKind::Other,
-            "path translation readlink failure!",
-        ));
+        return Err(Error::last_os_error());
     }
     let buf_read = buf_read as usize;
 
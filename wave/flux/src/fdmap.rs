@@ -0,0 +1,99 @@
+use crate::{rvec::RVec, types::*, unwrap_result};
+use RuntimeError::*;
+
+#[flux::constant]
+pub const MAX_SBOX_FDS: usize = 256;
+
+/// A host fd together with the rights the sandbox holds on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FdEntry {
+    pub fd: HostFd,
+    pub rights: Rights,
+}
+
+/// A directory the sandbox starts with: its sandbox fd and the name the guest
+/// knows it by.
+pub struct Preopen {
+    pub fd: u32,
+    pub name: RVec<u8>,
+}
+
+/// Per-sandbox fd table: maps sandbox fds (indices) to host fds and rights.
+pub struct FdMap {
+    m: RVec<Option<FdEntry>>,
+}
+
+impl Default for FdMap {
+    fn default() -> Self {
+        FdMap::new()
+    }
+}
+
+impl FdMap {
+    pub fn new() -> FdMap {
+        FdMap {
+            m: RVec::from_elem_n(None, MAX_SBOX_FDS),
+        }
+    }
+
+    /// Insert a host fd into the lowest free slot and return its sandbox fd.
+    pub fn create(&mut self, fd: HostFd, rights: Rights) -> RuntimeResult<u32> {
+        let mut idx = 0;
+        while idx < self.m.len() {
+            if self.m[idx].is_none() {
+                self.m[idx] = Some(FdEntry { fd, rights });
+                return Ok(idx as u32);
+            }
+            idx += 1;
+        }
+        Err(Emfile)
+    }
+
+    /// Number of sandbox fds currently in use.
+    pub fn open_count(&self) -> usize {
+        let mut count = 0;
+        let mut idx = 0;
+        while idx < self.m.len() {
+            if self.m[idx].is_some() {
+                count += 1;
+            }
+            idx += 1;
+        }
+        count
+    }
+
+    pub fn lookup(&self, fd: u32) -> RuntimeResult<FdEntry> {
+        let idx = fd as usize;
+        if idx >= self.m.len() {
+            return Err(Ebadf);
+        }
+        match self.m[idx] {
+            Some(entry) => Ok(entry),
+            None => Err(Ebadf),
+        }
+    }
+
+    /// Look up the host fd for `fd`, failing with `Enotcapable` unless the
+    /// entry holds every right in `needed`.
+    pub fn fd_to_native(&self, fd: u32, needed: Rights) -> RuntimeResult<HostFd> {
+        let entry = self.lookup(fd);
+        unwrap_result!(entry);
+        if !entry.rights.contains(needed) {
+            return Err(Enotcapable);
+        }
+        Ok(entry.fd)
+    }
+}
+
+impl VmCtx {
+    /// Give the sandbox access to host fd `fd` with `rights`, subject to its
+    /// open fd quota. Returns the new sandbox fd.
+    pub fn create_fd(&mut self, fd: HostFd, rights: Rights) -> RuntimeResult<u32> {
+        let _r = self.quota.check_open_fd(self.fdmap.open_count());
+        unwrap_result!(_r);
+        let v_fd = self.fdmap.create(fd, rights);
+        unwrap_result!(v_fd);
+        self.quota.reset_fd(v_fd);
+        Ok(v_fd)
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{rvec::RVec, types::*};
+use RuntimeError::*;
+
+/// How the host may touch a guarded range on the guest's behalf.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GuardKind {
+    /// Readable but not writable, e.g. data segments.
+    ReadOnly,
+    /// Neither readable nor writable, e.g. a stack guard.
+    Poisoned,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GuardRegion {
+    pub start: usize,
+    pub len: usize,
+    pub kind: GuardKind,
+}
+
+impl GuardRegion {
+    fn overlaps(&self, start: usize, len: usize) -> bool {
+        len > 0 && start < self.start + self.len && self.start < start + len
+    }
+
+    fn denies(&self, access: Access) -> bool {
+        match self.kind {
+            GuardKind::Poisoned => true,
+            GuardKind::ReadOnly => access == Access::Write,
+        }
+    }
+}
+
+/// Ranges of linear memory the host refuses to access for the guest.
+pub struct Guards {
+    regions: RVec<GuardRegion>,
+}
+
+impl Default for Guards {
+    fn default() -> Self {
+        Guards::new()
+    }
+}
+
+impl Guards {
+    pub fn new() -> Guards {
+        Guards {
+            regions: RVec::new(),
+        }
+    }
+
+    /// Whether `access` to `[start, start + len)` avoids every region that
+    /// forbids it.
+    pub fn allows(&self, start: usize, len: usize, access: Access) -> bool {
+        let mut idx = 0;
+        while idx < self.regions.len() {
+            let region = self.regions[idx];
+            if region.denies(access) && region.overlaps(start, len) {
+                return false;
+            }
+            idx += 1;
+        }
+        true
+    }
+}
+
+impl VmCtx {
+    /// Guard `[start, start + len)` of linear memory. Regions may overlap; the
+    /// strictest one wins.
+    pub fn add_guard(&mut self, start: usize, len: usize, kind: GuardKind) -> RuntimeResult<()> {
+        if len == 0 || !self.fits_in_lin_mem_usize(start, len) {
+            return Err(Einval);
+        }
+        self.guards.regions.push(GuardRegion { start, len, kind });
+        Ok(())
+    }
+
+    pub fn clear_guards(&mut self) {
+        self.guards = Guards::new();
+    }
+
+    /// `Efault` unless `[start, start + len)` is in linear memory and no
+    /// guard forbids `access` to it.
+    pub fn check_access(&self, start: usize, len: usize, access: Access) -> RuntimeResult<()> {
+        if !self.fits_in_lin_mem_usize(start, len) {
+            return Err(Efault);
+        }
+        self.check_guards(start, len, access)
+    }
+
+    /// Like `check_access`, for callers that have already bounds checked the
+    /// range.
+    pub fn check_guards(&self, start: usize, len: usize, access: Access) -> RuntimeResult<()> {
+        if !self.guards.allows(start, len, access) {
+            return Err(Efault);
+        }
+        Ok(())
+    }
+}
@@ -1,26 +1,75 @@
-use crate::{rvec::RVec, types::*, unwrap_result};
+use crate::layout::{decode_iovec, iovec};
+use crate::{guard::Access, rvec::RVec, types::*, unwrap_result};
 use RuntimeError::*;
 
-pub fn parse_iovs(ctx: &VmCtx, iovs: u32, iovcnt: u32) -> RuntimeResult<RVec<WasmIoVec>> {
+/// Decode `iovcnt` iovecs starting at `iovs`. `P` picks the layout: `u32`
+/// pairs for wasm32 guests and `u64` pairs for memory64 guests.
+pub fn parse_iovs<P: GuestPtr>(
+    ctx: &VmCtx,
+    iovs: P,
+    iovcnt: u32,
+) -> RuntimeResult<RVec<WasmIoVec>> {
+    let _r = ctx.quota.check_iovcnt(iovcnt);
+    unwrap_result!(_r);
+    let iovs: u64 = iovs.into();
+    let iov_size = iovec::size::<P>() as u64;
     let mut i = 0;
     let mut wasm_iovs = RVec::new();
     while i < iovcnt {
-        let start = (iovs + i * 8) as usize;
-        let v = ctx.read_u32_pair(start);
-        unwrap_result!(v);
-        let (ptr, len) = v;
+        let start = match iovs.checked_add((i as u64) * iov_size) {
+            Some(start) => start as usize,
+            None => return Err(Eoverflow),
+        };
+        let iov = decode_iovec::<P>(ctx, start);
+        unwrap_result!(iov);
 
-        if !ctx.fits_in_lin_mem(ptr, len) {
+        if !ctx.fits_in_lin_mem_usize(iov.iov_base, iov.iov_len) {
             return Err(Efault);
         }
 
-        wasm_iovs.push(WasmIoVec {
-            iov_base: ptr,
-            iov_len: len,
-        });
+        wasm_iovs.push(iov);
         i += 1;
     }
     assert(wasm_iovs.len() >= 0);
 
     Ok(wasm_iovs)
 }
+
+/// Total number of bytes described by `iovs`.
+pub fn iovs_len(iovs: &RVec<WasmIoVec>) -> u64 {
+    let mut total = 0;
+    let mut i = 0;
+    while i < iovs.len() {
+        total += iovs[i].iov_len as u64;
+        i += 1;
+    }
+    total
+}
+
+/// `Efault` if a guard forbids `access` to any of the buffers in `iovs`.
+pub fn check_iovs(ctx: &VmCtx, iovs: &RVec<WasmIoVec>, access: Access) -> RuntimeResult<()> {
+    let mut i = 0;
+    while i < iovs.len() {
+        let _r = ctx.check_access(iovs[i].iov_base, iovs[i].iov_len, access);
+        unwrap_result!(_r);
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Mark the first `n` bytes described by `iovs` dirty, after the host has
+/// written them on the guest's behalf.
+pub fn mark_iovs_dirty(ctx: &mut VmCtx, iovs: &RVec<WasmIoVec>, n: usize) {
+    let mut left = n;
+    let mut i = 0;
+    while i < iovs.len() && left > 0 {
+        let len = if iovs[i].iov_len < left {
+            iovs[i].iov_len
+        } else {
+            left
+        };
+        ctx.mark_dirty(iovs[i].iov_base, len);
+        left -= len;
+        i += 1;
+    }
+}
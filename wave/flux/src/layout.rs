@@ -0,0 +1,472 @@
+//! WASI preview1 layouts of the structs wave reads from and writes to guest
+//! memory, with their encoders and decoders.
+//!
+//! Each struct gets a module holding its size, alignment and field offsets.
+//! Only `iovec`/`ciovec` (pointers) and `prestat` (a `size`) hold guest
+//! pointer-sized fields, so only their layouts depend on the guest's pointer
+//! width; everything else is the same for wasm32 and memory64 guests.
+//!
+//! Decoders check the whole struct against linear memory and its guards
+//! before reading any of it, and encoders do the same before writing. Padding
+//! is always written as zeros.
+
+use crate::{guard::Access, types::*, unwrap_result};
+use RuntimeError::*;
+
+pub mod iovec {
+    use crate::types::GuestPtr;
+
+    pub const BUF: usize = 0;
+
+    pub fn size<P: GuestPtr>() -> usize {
+        2 * P::SIZE
+    }
+
+    pub fn align<P: GuestPtr>() -> usize {
+        P::SIZE
+    }
+
+    pub fn buf_len<P: GuestPtr>() -> usize {
+        P::SIZE
+    }
+}
+
+/// `ciovec` is laid out exactly like `iovec`.
+pub mod ciovec {
+    pub use super::iovec::*;
+}
+
+/// The `u.dir.pr_name_len` field is a `size`, so it is as wide and as
+/// aligned as a guest pointer.
+pub mod prestat {
+    use crate::types::GuestPtr;
+
+    pub const TAG: usize = 0;
+
+    /// `preopentype` of a directory, the only kind there is.
+    pub const TAG_DIR: u8 = 0;
+
+    pub fn size<P: GuestPtr>() -> usize {
+        2 * P::SIZE
+    }
+
+    pub fn align<P: GuestPtr>() -> usize {
+        P::SIZE
+    }
+
+    pub fn name_len<P: GuestPtr>() -> usize {
+        P::SIZE
+    }
+}
+
+pub mod fdstat {
+    #[flux::constant]
+    pub const SIZE: usize = 24;
+    pub const ALIGN: usize = 8;
+    #[flux::constant]
+    pub const FILETYPE: usize = 0;
+    #[flux::constant]
+    pub const FLAGS: usize = 2;
+    #[flux::constant]
+    pub const RIGHTS_BASE: usize = 8;
+    #[flux::constant]
+    pub const RIGHTS_INHERITING: usize = 16;
+}
+
+pub mod filestat {
+    #[flux::constant]
+    pub const SIZE: usize = 64;
+    pub const ALIGN: usize = 8;
+    #[flux::constant]
+    pub const DEV: usize = 0;
+    #[flux::constant]
+    pub const INO: usize = 8;
+    #[flux::constant]
+    pub const FILETYPE: usize = 16;
+    #[flux::constant]
+    pub const NLINK: usize = 24;
+    /// The `size` field.
+    #[flux::constant]
+    pub const FILE_SIZE: usize = 32;
+    #[flux::constant]
+    pub const ATIM: usize = 40;
+    #[flux::constant]
+    pub const MTIM: usize = 48;
+    #[flux::constant]
+    pub const CTIM: usize = 56;
+}
+
+pub mod dirent {
+    #[flux::constant]
+    pub const SIZE: usize = 24;
+    pub const ALIGN: usize = 8;
+    #[flux::constant]
+    pub const NEXT: usize = 0;
+    #[flux::constant]
+    pub const INO: usize = 8;
+    #[flux::constant]
+    pub const NAMLEN: usize = 16;
+    #[flux::constant]
+    pub const TYPE: usize = 20;
+}
+
+pub mod event {
+    #[flux::constant]
+    pub const SIZE: usize = 32;
+    pub const ALIGN: usize = 8;
+    #[flux::constant]
+    pub const USERDATA: usize = 0;
+    #[flux::constant]
+    pub const ERROR: usize = 8;
+    #[flux::constant]
+    pub const TYPE: usize = 10;
+    /// `fd_readwrite.nbytes`
+    #[flux::constant]
+    pub const NBYTES: usize = 16;
+    /// `fd_readwrite.flags`
+    #[flux::constant]
+    pub const FLAGS: usize = 24;
+
+    pub const FLAG_HANGUP: u16 = 1;
+}
+
+pub mod subscription {
+    #[flux::constant]
+    pub const SIZE: usize = 48;
+    pub const ALIGN: usize = 8;
+    #[flux::constant]
+    pub const USERDATA: usize = 0;
+    #[flux::constant]
+    pub const TAG: usize = 8;
+    /// `u.clock.id`
+    #[flux::constant]
+    pub const CLOCK_ID: usize = 16;
+    /// `u.clock.timeout`
+    #[flux::constant]
+    pub const CLOCK_TIMEOUT: usize = 24;
+    /// `u.clock.precision`
+    #[flux::constant]
+    pub const CLOCK_PRECISION: usize = 32;
+    /// `u.clock.flags`
+    #[flux::constant]
+    pub const CLOCK_FLAGS: usize = 40;
+    /// `u.fd_read.file_descriptor` and `u.fd_write.file_descriptor`
+    #[flux::constant]
+    pub const FD: usize = 16;
+
+    pub const CLOCK_FLAG_ABSTIME: u16 = 1;
+}
+
+/// Write `size` bytes of zeros at `ptr`; `size` is a multiple of 8.
+#[flux::sig(fn(&mut VmCtx[@cx], ptr: usize, size: usize{0 <= ptr && ptr <= ptr + size && ptr + size < LINEAR_MEM_SIZE && size % 8 == 0}) -> Result<(), RuntimeError>)]
+fn zero(ctx: &mut VmCtx, ptr: usize, size: usize) -> RuntimeResult<()> {
+    let mut off = 0;
+    while off < size {
+        let _r = ctx.write_u64(ptr + off, 0);
+        unwrap_result!(_r);
+        off += 8;
+    }
+    Ok(())
+}
+
+fn decode_filetype(v: u8) -> RuntimeResult<Filetype> {
+    match Filetype::from_u8(v) {
+        Some(t) => Ok(t),
+        None => Err(Einval),
+    }
+}
+
+/// Out-of-bounds iovecs are `Eoverflow`, like every other read of an iovec
+/// array.
+pub fn decode_iovec<P: GuestPtr>(ctx: &VmCtx, ptr: usize) -> RuntimeResult<WasmIoVec> {
+    let v = ctx.read_ptr_pair::<P>(ptr + iovec::BUF);
+    unwrap_result!(v);
+    let (buf, buf_len) = v;
+    Ok(WasmIoVec {
+        iov_base: buf as usize,
+        iov_len: buf_len as usize,
+    })
+}
+
+pub fn encode_iovec<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    ptr: usize,
+    iov: &WasmIoVec,
+) -> RuntimeResult<()> {
+    if !ctx.fits_in_lin_mem_usize(ptr, iovec::size::<P>()) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, iovec::size::<P>(), Access::Write);
+    unwrap_result!(_r);
+    let _r = ctx.write_ptr::<P>(ptr + iovec::BUF, iov.iov_base as u64);
+    unwrap_result!(_r);
+    ctx.write_ptr::<P>(ptr + iovec::buf_len::<P>(), iov.iov_len as u64)
+}
+
+// The tag and its padding fill the first pointer-sized word of a `prestat`,
+// so both are read and written as one guest pointer.
+
+pub fn decode_prestat<P: GuestPtr>(ctx: &VmCtx, ptr: usize) -> RuntimeResult<Prestat> {
+    if !ctx.fits_in_lin_mem_usize(ptr, prestat::size::<P>()) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, prestat::size::<P>(), Access::Read);
+    unwrap_result!(_r);
+    let tag = ctx.read_ptr::<P>(ptr + prestat::TAG);
+    unwrap_result!(tag);
+    if tag as u8 != prestat::TAG_DIR {
+        return Err(Einval);
+    }
+    let name_len = ctx.read_ptr::<P>(ptr + prestat::name_len::<P>());
+    unwrap_result!(name_len);
+    if name_len > u32::MAX as u64 {
+        return Err(Eoverflow);
+    }
+    Ok(Prestat {
+        name_len: name_len as u32,
+    })
+}
+
+pub fn encode_prestat<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    ptr: usize,
+    stat: &Prestat,
+) -> RuntimeResult<()> {
+    if !ctx.fits_in_lin_mem_usize(ptr, prestat::size::<P>()) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, prestat::size::<P>(), Access::Write);
+    unwrap_result!(_r);
+    let _r = ctx.write_ptr::<P>(ptr + prestat::TAG, prestat::TAG_DIR as u64);
+    unwrap_result!(_r);
+    ctx.write_ptr::<P>(ptr + prestat::name_len::<P>(), stat.name_len as u64)
+}
+
+pub fn decode_fdstat(ctx: &VmCtx, ptr: usize) -> RuntimeResult<Fdstat> {
+    if !ctx.fits_in_lin_mem_usize(ptr, fdstat::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, fdstat::SIZE, Access::Read);
+    unwrap_result!(_r);
+    let filetype = decode_filetype(ctx.read_u8(ptr + fdstat::FILETYPE));
+    unwrap_result!(filetype);
+    Ok(Fdstat {
+        filetype,
+        flags: ctx.read_u16(ptr + fdstat::FLAGS),
+        rights_base: Rights::from_bits(ctx.read_u64(ptr + fdstat::RIGHTS_BASE)),
+        rights_inheriting: Rights::from_bits(ctx.read_u64(ptr + fdstat::RIGHTS_INHERITING)),
+    })
+}
+
+pub fn encode_fdstat(ctx: &mut VmCtx, ptr: usize, stat: &Fdstat) -> RuntimeResult<()> {
+    if !ctx.fits_in_lin_mem_usize(ptr, fdstat::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, fdstat::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let _r = zero(ctx, ptr, fdstat::SIZE);
+    unwrap_result!(_r);
+    let _r = ctx.write_u8(ptr + fdstat::FILETYPE, stat.filetype as u8);
+    unwrap_result!(_r);
+    let _r = ctx.write_u16(ptr + fdstat::FLAGS, stat.flags);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + fdstat::RIGHTS_BASE, stat.rights_base.bits());
+    unwrap_result!(_r);
+    ctx.write_u64(
+        ptr + fdstat::RIGHTS_INHERITING,
+        stat.rights_inheriting.bits(),
+    )
+}
+
+pub fn decode_filestat(ctx: &VmCtx, ptr: usize) -> RuntimeResult<Filestat> {
+    if !ctx.fits_in_lin_mem_usize(ptr, filestat::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, filestat::SIZE, Access::Read);
+    unwrap_result!(_r);
+    let filetype = decode_filetype(ctx.read_u8(ptr + filestat::FILETYPE));
+    unwrap_result!(filetype);
+    Ok(Filestat {
+        dev: ctx.read_u64(ptr + filestat::DEV),
+        ino: ctx.read_u64(ptr + filestat::INO),
+        filetype,
+        nlink: ctx.read_u64(ptr + filestat::NLINK),
+        size: ctx.read_u64(ptr + filestat::FILE_SIZE),
+        atim: ctx.read_u64(ptr + filestat::ATIM),
+        mtim: ctx.read_u64(ptr + filestat::MTIM),
+        ctim: ctx.read_u64(ptr + filestat::CTIM),
+    })
+}
+
+pub fn encode_filestat(ctx: &mut VmCtx, ptr: usize, stat: &Filestat) -> RuntimeResult<()> {
+    if !ctx.fits_in_lin_mem_usize(ptr, filestat::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, filestat::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let _r = zero(ctx, ptr, filestat::SIZE);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + filestat::DEV, stat.dev);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + filestat::INO, stat.ino);
+    unwrap_result!(_r);
+    let _r = ctx.write_u8(ptr + filestat::FILETYPE, stat.filetype as u8);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + filestat::NLINK, stat.nlink);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + filestat::FILE_SIZE, stat.size);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + filestat::ATIM, stat.atim);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + filestat::MTIM, stat.mtim);
+    unwrap_result!(_r);
+    ctx.write_u64(ptr + filestat::CTIM, stat.ctim)
+}
+
+pub fn decode_dirent(ctx: &VmCtx, ptr: usize) -> RuntimeResult<Dirent> {
+    if !ctx.fits_in_lin_mem_usize(ptr, dirent::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, dirent::SIZE, Access::Read);
+    unwrap_result!(_r);
+    let filetype = decode_filetype(ctx.read_u8(ptr + dirent::TYPE));
+    unwrap_result!(filetype);
+    Ok(Dirent {
+        next: ctx.read_u64(ptr + dirent::NEXT),
+        ino: ctx.read_u64(ptr + dirent::INO),
+        namlen: ctx.read_u32(ptr + dirent::NAMLEN),
+        filetype,
+    })
+}
+
+pub fn encode_dirent(ctx: &mut VmCtx, ptr: usize, ent: &Dirent) -> RuntimeResult<()> {
+    if !ctx.fits_in_lin_mem_usize(ptr, dirent::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, dirent::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let _r = zero(ctx, ptr, dirent::SIZE);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + dirent::NEXT, ent.next);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + dirent::INO, ent.ino);
+    unwrap_result!(_r);
+    let _r = ctx.write_u32(ptr + dirent::NAMLEN, ent.namlen);
+    unwrap_result!(_r);
+    ctx.write_u8(ptr + dirent::TYPE, ent.filetype as u8)
+}
+
+pub fn decode_event(ctx: &VmCtx, ptr: usize) -> RuntimeResult<Event> {
+    if !ctx.fits_in_lin_mem_usize(ptr, event::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, event::SIZE, Access::Read);
+    unwrap_result!(_r);
+    let error = match RuntimeError::from_wasi_errno(ctx.read_u16(ptr + event::ERROR)) {
+        Some(e) => e,
+        None => return Err(Einval),
+    };
+    let ty = match EventType::from_u8(ctx.read_u8(ptr + event::TYPE)) {
+        Some(ty) => ty,
+        None => return Err(Einval),
+    };
+    Ok(Event {
+        userdata: ctx.read_u64(ptr + event::USERDATA),
+        error,
+        ty,
+        nbytes: ctx.read_u64(ptr + event::NBYTES),
+        hangup: ctx.read_u16(ptr + event::FLAGS) & event::FLAG_HANGUP != 0,
+    })
+}
+
+pub fn encode_event(ctx: &mut VmCtx, ptr: usize, ev: &Event) -> RuntimeResult<()> {
+    if !ctx.fits_in_lin_mem_usize(ptr, event::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, event::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let flags = if ev.hangup { event::FLAG_HANGUP } else { 0 };
+    let _r = zero(ctx, ptr, event::SIZE);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + event::USERDATA, ev.userdata);
+    unwrap_result!(_r);
+    let _r = ctx.write_u16(ptr + event::ERROR, ev.error.to_wasi_errno());
+    unwrap_result!(_r);
+    let _r = ctx.write_u8(ptr + event::TYPE, ev.ty as u8);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + event::NBYTES, ev.nbytes);
+    unwrap_result!(_r);
+    ctx.write_u16(ptr + event::FLAGS, flags)
+}
+
+/// Decode a `subscription`. An unknown tag is `Einval`; an unknown clock is
+/// kept for the caller to report in its event.
+pub fn decode_subscription(ctx: &VmCtx, ptr: usize) -> RuntimeResult<Subscription> {
+    if !ctx.fits_in_lin_mem_usize(ptr, subscription::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, subscription::SIZE, Access::Read);
+    unwrap_result!(_r);
+    let kind = match ctx.read_u8(ptr + subscription::TAG) {
+        0 => SubscriptionKind::Clock {
+            id: ctx.read_u32(ptr + subscription::CLOCK_ID),
+            timeout: ctx.read_u64(ptr + subscription::CLOCK_TIMEOUT),
+            precision: ctx.read_u64(ptr + subscription::CLOCK_PRECISION),
+            abstime: ctx.read_u16(ptr + subscription::CLOCK_FLAGS)
+                & subscription::CLOCK_FLAG_ABSTIME
+                != 0,
+        },
+        1 => SubscriptionKind::FdRead(ctx.read_u32(ptr + subscription::FD)),
+        2 => SubscriptionKind::FdWrite(ctx.read_u32(ptr + subscription::FD)),
+        _ => return Err(Einval),
+    };
+    Ok(Subscription {
+        userdata: ctx.read_u64(ptr + subscription::USERDATA),
+        kind,
+    })
+}
+
+pub fn encode_subscription(ctx: &mut VmCtx, ptr: usize, sub: &Subscription) -> RuntimeResult<()> {
+    if !ctx.fits_in_lin_mem_usize(ptr, subscription::SIZE) {
+        return Err(Efault);
+    }
+    let _r = ctx.check_guards(ptr, subscription::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let _r = zero(ctx, ptr, subscription::SIZE);
+    unwrap_result!(_r);
+    let _r = ctx.write_u64(ptr + subscription::USERDATA, sub.userdata);
+    unwrap_result!(_r);
+    match sub.kind {
+        SubscriptionKind::Clock {
+            id,
+            timeout,
+            precision,
+            abstime,
+        } => {
+            let flags = if abstime {
+                subscription::CLOCK_FLAG_ABSTIME
+            } else {
+                0
+            };
+            let _r = ctx.write_u8(ptr + subscription::TAG, EventType::Clock as u8);
+            unwrap_result!(_r);
+            let _r = ctx.write_u32(ptr + subscription::CLOCK_ID, id);
+            unwrap_result!(_r);
+            let _r = ctx.write_u64(ptr + subscription::CLOCK_TIMEOUT, timeout);
+            unwrap_result!(_r);
+            let _r = ctx.write_u64(ptr + subscription::CLOCK_PRECISION, precision);
+            unwrap_result!(_r);
+            ctx.write_u16(ptr + subscription::CLOCK_FLAGS, flags)
+        }
+        SubscriptionKind::FdRead(fd) => {
+            let _r = ctx.write_u8(ptr + subscription::TAG, EventType::FdRead as u8);
+            unwrap_result!(_r);
+            ctx.write_u32(ptr + subscription::FD, fd)
+        }
+        SubscriptionKind::FdWrite(fd) => {
+            let _r = ctx.write_u8(ptr + subscription::TAG, EventType::FdWrite as u8);
+            unwrap_result!(_r);
+            ctx.write_u32(ptr + subscription::FD, fd)
+        }
+    }
+}
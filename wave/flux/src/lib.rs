@@ -5,9 +5,18 @@
 #![feature(custom_inner_attributes)]
 #![flux::qualifier(MyQ1(x: int, y: int, a: int) : x + y <= a + LINEAR_MEM_SIZE)]
 
+pub mod fdmap;
+pub mod guard;
 pub mod iov;
+pub mod layout;
+pub mod link_cache;
 pub mod path_resolution;
+pub mod poll;
+pub mod policy;
+pub mod quota;
 pub mod runtime;
 pub mod rvec;
+pub mod snapshot;
 pub mod tcb;
 pub mod types;
+pub mod wrappers;
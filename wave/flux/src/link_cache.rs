@@ -0,0 +1,149 @@
+use std::path::{Path, PathBuf};
+
+use crate::{rvec::RVec, tcb::os::os_file_id, types::*};
+
+/// Default number of prefixes a sandbox remembers.
+pub const LINK_CACHE_SIZE: usize = 128;
+
+/// What `readlinkat` said about a path prefix. Failures other than "not a
+/// symlink" are not cached: a prefix that is missing now may be created as a
+/// symlink later.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CachedLink {
+    /// There, but not a symlink: the prefix counts towards `ns_prefix`
+    /// without another syscall.
+    NotALink,
+    /// A symlink with this target.
+    Link(PathBuf),
+}
+
+/// The directory a path is resolved under, as (device, inode). Unlike the
+/// dirfd number, this cannot come to name a different directory while the
+/// dirfd stays open, and a closed dirfd's number being reused does not
+/// inherit its entries.
+type DirId = (u64, u64);
+
+struct CacheEntry {
+    dir: DirId,
+    prefix: PathBuf,
+    link: CachedLink,
+}
+
+/// Per-sandbox memo of `readlinkat` results, keyed by (directory, component
+/// prefix), so that resolving paths under the same directories does not
+/// re-read every prefix. Holds at most `capacity` entries and evicts the
+/// oldest first.
+///
+/// The cache trusts that existing paths only change through the sandbox,
+/// and `read_linkat_h`'s `ns_prefix` guarantee rests on that. Anything that
+/// removes, renames or replaces a path must call `clear`:
+/// - the `path_unlink_file`, `path_remove_directory` and `path_rename`
+///   wrappers, when they are added (this tree has none yet);
+/// - `VmCtx::restore`, which does;
+/// - an embedder that changes the preopened directories behind the sandbox's
+///   back.
+///
+/// Wrappers that only create paths (`path_create_directory`, `path_symlink`,
+/// `path_link`, `path_open` with `O_CREAT`) need not: missing prefixes are
+/// never cached. Closing or renumbering a dirfd needs nothing either, since
+/// entries are keyed by directory identity rather than fd number.
+pub struct LinkCache {
+    entries: RVec<CacheEntry>,
+    // directory the current resolution is under, set by `enter`; `None`
+    // bypasses the cache
+    dir: Option<DirId>,
+    capacity: usize,
+    // slot the next insert overwrites once the cache is full
+    next: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl Default for LinkCache {
+    fn default() -> Self {
+        LinkCache::new(LINK_CACHE_SIZE)
+    }
+}
+
+impl LinkCache {
+    /// A cache holding up to `capacity` prefixes. A capacity of 0 disables
+    /// caching.
+    pub fn new(capacity: usize) -> LinkCache {
+        LinkCache {
+            entries: RVec::new(),
+            dir: None,
+            capacity,
+            next: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Start answering lookups for paths under `dirfd`. If `dirfd` cannot
+    /// be identified the cache is bypassed until the next `enter`.
+    pub fn enter(&mut self, dirfd: HostFd) {
+        self.dir = if self.capacity == 0 {
+            None
+        } else {
+            os_file_id(dirfd).ok()
+        };
+    }
+
+    /// What the current directory's entry for `prefix` says, if there is one.
+    pub fn lookup(&mut self, prefix: &Path) -> Option<CachedLink> {
+        let dir = match self.dir {
+            Some(dir) => dir,
+            None => return None,
+        };
+        let mut idx = 0;
+        while idx < self.entries.len() {
+            let entry = &self.entries[idx];
+            if entry.dir == dir && entry.prefix == prefix {
+                self.hits += 1;
+                return Some(entry.link.clone());
+            }
+            idx += 1;
+        }
+        self.misses += 1;
+        None
+    }
+
+    pub fn insert(&mut self, prefix: PathBuf, link: CachedLink) {
+        let dir = match self.dir {
+            Some(dir) => dir,
+            None => return,
+        };
+        let entry = CacheEntry { dir, prefix, link };
+        if self.entries.len() < self.capacity {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.next] = entry;
+            self.next = (self.next + 1) % self.capacity;
+        }
+    }
+
+    /// Forget everything. Call after any change to the paths the sandbox can
+    /// see.
+    pub fn clear(&mut self) {
+        self.entries = RVec::new();
+        self.next = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.len() == 0
+    }
+
+    /// Lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Lookups that had to call `readlinkat`.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
@@ -1,6 +1,9 @@
+use crate::link_cache::LinkCache;
 use crate::rvec::RVec;
+use crate::tcb::os::{os_open_path, os_openat2_beneath};
 use crate::tcb::path::*;
 use crate::types::*;
+use crate::unwrap_result;
 use std::ffi::OsString;
 use std::os::unix::ffi::OsStringExt;
 use std::path::PathBuf;
@@ -11,11 +14,102 @@ fn to_pathbuf(v: RVec<u8>) -> PathBuf {
     PathBuf::from(OsString::from_vec(v.to_vec()))
 }
 
-#[flux::sig(fn (RVec<u8>, should_follow:bool, HostFd) -> Result<LastSymLink[should_follow], RuntimeError>)]
+/// The rule a rejected path broke.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// The path has no components.
+    Empty,
+    /// `min_depth < 0`: the path climbs above its directory fd.
+    DepthBelowZero,
+    /// `!is_relative`: the path, or a symlink in it, is absolute.
+    NotRelative,
+    /// More than `MAXSYMLINKS` symlinks were expanded.
+    TooManySymlinks,
+    /// The expanded path does not fit in `PATH_MAX`.
+    TooLong,
+}
+
+/// Why `diagnose_path` rejected a path.
+pub struct Rejection {
+    /// The errno `resolve_path` returns for the same path.
+    pub errno: RuntimeError,
+    pub rule: Rule,
+    /// Index of the component of the original path being expanded when the
+    /// rule was first broken, if the rule is tied to one.
+    pub component: Option<usize>,
+    /// Targets of the symlinks expanded so far, in the order they were read.
+    pub symlinks: RVec<PathBuf>,
+}
+
+/// Record of a manual walk, kept by `diagnose_path` and ignored otherwise.
+struct ResolveTrace {
+    enabled: bool,
+    component: usize,
+    violation: Option<(usize, Rule)>,
+    symlinks: RVec<PathBuf>,
+}
+
+impl ResolveTrace {
+    fn new(enabled: bool) -> ResolveTrace {
+        ResolveTrace {
+            enabled,
+            component: 0,
+            violation: None,
+            symlinks: RVec::new(),
+        }
+    }
+
+    fn begin_component(&mut self, idx: usize) {
+        self.component = idx;
+    }
+
+    #[flux::trusted]
+    fn record_symlink(&mut self, linkpath: &FOwnedComponents) {
+        if self.enabled {
+            self.symlinks.push(linkpath.as_pathbuf());
+        }
+    }
+
+    // Paths only grow during the walk, so the first prefix that breaks a
+    // rule is where the final path check will fail.
+    #[flux::trusted]
+    fn check(&mut self, out_path: &FOwnedComponents) {
+        if !self.enabled || self.violation.is_some() || out_path.len() == 0 {
+            return;
+        }
+        let depth = min_depth(out_path);
+        if !is_relative(out_path) || depth == DEPTH_ERR {
+            self.violation = Some((self.component, Rule::NotRelative));
+        } else if depth < 0 {
+            self.violation = Some((self.component, Rule::DepthBelowZero));
+        }
+    }
+
+    fn into_rejection(self, errno: RuntimeError) -> Rejection {
+        let (component, rule) = match errno {
+            RuntimeError::Eloop => (Some(self.component), Rule::TooManySymlinks),
+            RuntimeError::Enametoolong => (None, Rule::TooLong),
+            _ => match self.violation {
+                Some((idx, rule)) => (Some(idx), rule),
+                None => (None, Rule::Empty),
+            },
+        };
+        Rejection {
+            errno,
+            rule,
+            component,
+            symlinks: self.symlinks,
+        }
+    }
+}
+
+#[flux::sig(fn (RVec<u8>, should_follow:bool, HostFd, &mut ResolveTrace, &mut LinkCache) -> Result<LastSymLink[should_follow], RuntimeError>)]
 fn expand_path(
     vec: RVec<u8>,
     should_follow: bool,
     dirfd: HostFd,
+    trace: &mut ResolveTrace,
+    cache: &mut LinkCache,
 ) -> Result<FOwnedComponents, RuntimeError> {
     let p = to_pathbuf(vec);
     let components = get_components(&p);
@@ -26,21 +120,33 @@ fn expand_path(
     let mut idx = 0;
     while idx < components.len() {
         let c = components[idx].clone();
+        trace.begin_component(idx);
         // if this is the last element, and we are NO_FOLLOW, then don't expand
         if !should_follow && idx + 1 == components.len() {
             out_path.push(c);
+            trace.check(&out_path);
             break;
         }
         // if comp is a symlink, return path + update num_symlinks
         // if not, just extend out_path
-        let maybe_linkpath = maybe_expand_component(dirfd, &mut out_path, c, &mut num_symlinks);
+        let maybe_linkpath =
+            maybe_expand_component(dirfd, &mut out_path, c, &mut num_symlinks, cache);
 
         if let Some(linkpath) = maybe_linkpath {
-            expand_symlink(&mut out_path, linkpath, &mut num_symlinks, dirfd);
+            trace.record_symlink(&linkpath);
+            expand_symlink(
+                &mut out_path,
+                linkpath,
+                &mut num_symlinks,
+                dirfd,
+                trace,
+                cache,
+            );
         }
         if num_symlinks >= MAXSYMLINKS {
             return Err(RuntimeError::Eloop);
         }
+        trace.check(&out_path);
         idx += 1;
     }
     //assert!(!should_follow || (should_follow && !is_symlink(&out_path)));
@@ -52,9 +158,55 @@ pub fn resolve_path(
     path: RVec<u8>,
     should_follow: bool,
     dirfd: HostFd,
+) -> Result<HostPath, RuntimeError> {
+    resolve_path_cached(path, should_follow, dirfd, &mut LinkCache::new(0))
+}
+
+/// `resolve_path`, answering `readlinkat` lookups from `cache` where it can
+/// and remembering the ones it has to make.
+#[flux::sig(fn(RVec<u8>, should_follow:bool, HostFd, &mut LinkCache) -> Result<HostPathSafe[should_follow], RuntimeError>)]
+pub fn resolve_path_cached(
+    path: RVec<u8>,
+    should_follow: bool,
+    dirfd: HostFd,
+    cache: &mut LinkCache,
+) -> Result<HostPath, RuntimeError> {
+    cache.enter(dirfd);
+    resolve_path_traced(
+        path,
+        should_follow,
+        dirfd,
+        &mut ResolveTrace::new(false),
+        cache,
+    )
+}
+
+/// `resolve_path`, but a rejection says which rule the path broke, at which
+/// component, and which symlinks had been expanded by then. For logging and
+/// debugging only: wasi calls return `resolve_path`'s plain errno.
+pub fn diagnose_path(
+    path: RVec<u8>,
+    should_follow: bool,
+    dirfd: HostFd,
+) -> Result<HostPath, Rejection> {
+    let mut trace = ResolveTrace::new(true);
+    let mut cache = LinkCache::new(0);
+    match resolve_path_traced(path, should_follow, dirfd, &mut trace, &mut cache) {
+        Ok(p) => Ok(p),
+        Err(e) => Err(trace.into_rejection(e)),
+    }
+}
+
+#[flux::sig(fn(RVec<u8>, should_follow:bool, HostFd, &mut ResolveTrace, &mut LinkCache) -> Result<HostPathSafe[should_follow], RuntimeError>)]
+fn resolve_path_traced(
+    path: RVec<u8>,
+    should_follow: bool,
+    dirfd: HostFd,
+    trace: &mut ResolveTrace,
+    cache: &mut LinkCache,
 ) -> Result<HostPath, RuntimeError> {
     // TODO: use ? when that works properly in Prusti
-    let c = expand_path(path, should_follow, dirfd);
+    let c = expand_path(path, should_follow, dirfd, trace, cache);
 
     let c = match c {
         Ok(oc) => oc,
@@ -73,14 +225,52 @@ pub fn resolve_path(
     }
 }
 
+/// How `resolve_fd` keeps a path beneath its directory fd.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolver {
+    /// Expand symlinks ourselves with `readlinkat` (`resolve_path`), then open
+    /// the result. A symlink swapped in between the two steps is not noticed.
+    Manual,
+    /// Let the kernel resolve the path with `openat2` and
+    /// `RESOLVE_BENEATH | RESOLVE_NO_MAGICLINKS`, falling back to `Manual` on
+    /// hosts without `openat2`.
+    Openat2,
+}
+
+/// Resolve `path` beneath `dirfd` and open whatever it names as an `O_PATH`
+/// fd, so that later operations act on the file that was checked.
+pub fn resolve_fd(
+    path: RVec<u8>,
+    should_follow: bool,
+    dirfd: HostFd,
+    resolver: Resolver,
+) -> Result<HostFd, RuntimeError> {
+    // neither backend can represent an interior NUL; reject it up front so
+    // they agree
+    if path.as_slice().contains(&0) {
+        return Err(RuntimeError::Einval);
+    }
+    if resolver == Resolver::Openat2 {
+        match os_openat2_beneath(dirfd, path.as_slice(), should_follow) {
+            Err(RuntimeError::Enosys) => {}
+            r => return r,
+        }
+    }
+    let host_path = resolve_path(path, should_follow, dirfd);
+    unwrap_result!(host_path);
+    os_open_path(dirfd, host_path.as_bytes())
+}
+
 // Recursively expands a symlink (without explicit recursion)
 // maintains a queue of path components to process
-#[flux::sig(fn(out_path: &mut NoSymLinks, linkpath: FOwnedComponents, num_symlinks: &mut isize, HostFd))]
+#[flux::sig(fn(out_path: &mut NoSymLinks, linkpath: FOwnedComponents, num_symlinks: &mut isize, HostFd, &mut ResolveTrace, &mut LinkCache))]
 fn expand_symlink(
     out_path: &mut FOwnedComponents,
     linkpath_components: FOwnedComponents,
     num_symlinks: &mut isize,
     dirfd: HostFd,
+    trace: &mut ResolveTrace,
+    cache: &mut LinkCache,
 ) {
     let mut idx = 0;
     while idx < linkpath_components.len() {
@@ -89,9 +279,10 @@ fn expand_symlink(
             return;
         }
         let c = linkpath_components.lookup(idx);
-        let maybe_linkpath = maybe_expand_component(dirfd, out_path, c, num_symlinks);
+        let maybe_linkpath = maybe_expand_component(dirfd, out_path, c, num_symlinks, cache);
         if let Some(linkpath) = maybe_linkpath {
-            expand_symlink(out_path, linkpath, num_symlinks, dirfd);
+            trace.record_symlink(&linkpath);
+            expand_symlink(out_path, linkpath, num_symlinks, dirfd, trace, cache);
         }
         idx += 1;
     }
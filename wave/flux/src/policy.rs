@@ -0,0 +1,295 @@
+//! Sandbox policy files: a declarative description of what a sandbox may
+//! touch, loaded into a ready-to-run `VmCtx`.
+//!
+//! A policy is a list of directives, one per line. Blank lines and lines
+//! starting with `#` are ignored, and fields are separated by whitespace.
+//! There are no trailing comments: a `#` after a directive is part of it.
+//!
+//! ```text
+//! preopen HOST_DIR GUEST_NAME [RIGHT...]
+//! arg VALUE
+//! env KEY=VALUE
+//! quota LIMIT VALUE
+//! net tcp|udp ADDR:PORT
+//! raise deny|ignore|terminate
+//! ```
+//!
+//! `preopen` rights default to all. `arg` takes the rest of the line.
+//! `quota` LIMIT is a `QuotaLimits` field. `net` ADDR is dotted IPv4. `raise`
+//! picks what `proc_raise` does, deny by default.
+
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use crate::fdmap::Preopen;
+use crate::quota::{Quota, QuotaLimits};
+use crate::rvec::RVec;
+use crate::tcb::os::{os_close, os_open_dir};
+use crate::tcb::path::{NetEndpoint, WasiProto};
+use crate::types::{RaisePolicy, Rights, VmCtx};
+
+/// `VmCtx` keeps fewer than this many args and env vars.
+const MAX_ARGS: usize = 1024;
+
+/// A malformed or unloadable policy, with the line it came from.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PolicyError {
+    /// 1-based, or `None` for errors about the file as a whole.
+    pub line: Option<usize>,
+    pub msg: String,
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.msg),
+            None => write!(f, "{}", self.msg),
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+fn error_at(line: usize, msg: String) -> PolicyError {
+    PolicyError {
+        line: Some(line),
+        msg,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreopenSpec {
+    pub host: String,
+    pub guest: String,
+    pub rights: Rights,
+    /// Where the directive was, for errors while opening `host`.
+    pub line: usize,
+}
+
+/// A parsed policy, not yet applied to a sandbox.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    pub preopens: Vec<PreopenSpec>,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub limits: QuotaLimits,
+    pub net: Vec<NetEndpoint>,
+    pub raise: RaisePolicy,
+}
+
+fn parse_preopen(fields: &str, line: usize) -> Result<PreopenSpec, String> {
+    let mut fields = fields.split_whitespace();
+    let (host, guest) = match (fields.next(), fields.next()) {
+        (Some(host), Some(guest)) => (host, guest),
+        _ => return Err("expected `preopen HOST_DIR GUEST_NAME [RIGHT...]`".to_string()),
+    };
+    let mut rights = Rights::empty();
+    let mut any = false;
+    for name in fields {
+        let right = match name {
+            "all" => Rights::all(),
+            _ => Rights::from_name(name).ok_or(format!("unknown right `{}`", name))?,
+        };
+        rights = rights | right;
+        any = true;
+    }
+    Ok(PreopenSpec {
+        host: host.to_string(),
+        guest: guest.to_string(),
+        rights: if any { rights } else { Rights::all() },
+        line,
+    })
+}
+
+fn parse_quota(fields: &str, limits: &mut QuotaLimits) -> Result<(), String> {
+    let (name, value) = match fields.split_once(char::is_whitespace) {
+        Some((name, value)) => (name, value.trim()),
+        None => return Err("expected `quota LIMIT VALUE`".to_string()),
+    };
+    let bad_value = |_| format!("invalid value `{}` for quota `{}`", value, name);
+    match name {
+        "max_open_fds" => limits.max_open_fds = value.parse().map_err(bad_value)?,
+        "max_bytes_written_per_fd" => {
+            limits.max_bytes_written_per_fd = value.parse().map_err(bad_value)?
+        }
+        "max_bytes_written" => limits.max_bytes_written = value.parse().map_err(bad_value)?,
+        "max_path_resolutions_per_sec" => {
+            limits.max_path_resolutions_per_sec = value.parse().map_err(bad_value)?
+        }
+        "max_iovecs" => limits.max_iovecs = value.parse().map_err(bad_value)?,
+        _ => return Err(format!("unknown quota `{}`", name)),
+    }
+    Ok(())
+}
+
+fn parse_net(fields: &str) -> Result<NetEndpoint, String> {
+    let mut fields = fields.split_whitespace();
+    let (protocol, endpoint) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(protocol), Some(endpoint), None) => (protocol, endpoint),
+        _ => return Err("expected `net tcp|udp ADDR:PORT`".to_string()),
+    };
+    let protocol = match protocol {
+        "tcp" => WasiProto::Tcp,
+        "udp" => WasiProto::Udp,
+        _ => return Err(format!("unknown protocol `{}`", protocol)),
+    };
+    let bad_endpoint = || format!("invalid endpoint `{}`", endpoint);
+    let (addr, port) = endpoint.rsplit_once(':').ok_or_else(bad_endpoint)?;
+    let addr: Ipv4Addr = addr.parse().map_err(|_| bad_endpoint())?;
+    let port: u16 = port.parse().map_err(|_| bad_endpoint())?;
+    Ok(NetEndpoint {
+        protocol,
+        addr: u32::from(addr),
+        port: port as u32,
+    })
+}
+
+fn parse_raise(fields: &str) -> Result<RaisePolicy, String> {
+    match fields {
+        "deny" => Ok(RaisePolicy::Deny),
+        "ignore" => Ok(RaisePolicy::Ignore),
+        "terminate" => Ok(RaisePolicy::Terminate),
+        _ => Err("expected `raise deny|ignore|terminate`".to_string()),
+    }
+}
+
+fn check_no_nul(s: &str) -> Result<(), String> {
+    if s.contains('\0') {
+        return Err("NUL bytes are not allowed".to_string());
+    }
+    Ok(())
+}
+
+impl Policy {
+    #[flux::trusted]
+    pub fn parse(text: &str) -> Result<Policy, PolicyError> {
+        let mut policy = Policy::default();
+        for (idx, content) in text.lines().enumerate() {
+            let line = idx + 1;
+            let content = content.trim();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let (directive, rest) = match content.split_once(char::is_whitespace) {
+                Some((directive, rest)) => (directive, rest.trim()),
+                None => (content, ""),
+            };
+            let parsed = check_no_nul(rest).and_then(|_| match directive {
+                "preopen" => {
+                    let spec = parse_preopen(rest, line)?;
+                    if policy.preopens.iter().any(|p| p.guest == spec.guest) {
+                        return Err(format!("duplicate preopen `{}`", spec.guest));
+                    }
+                    policy.preopens.push(spec);
+                    Ok(())
+                }
+                "arg" => {
+                    if policy.args.len() + 1 >= MAX_ARGS {
+                        return Err(format!("more than {} args", MAX_ARGS - 1));
+                    }
+                    policy.args.push(rest.to_string());
+                    Ok(())
+                }
+                "env" => {
+                    let (key, value) = match rest.split_once('=') {
+                        Some((key, value)) if !key.is_empty() => (key, value),
+                        _ => return Err("expected `env KEY=VALUE`".to_string()),
+                    };
+                    if policy.env.len() + 1 >= MAX_ARGS {
+                        return Err(format!("more than {} env vars", MAX_ARGS - 1));
+                    }
+                    policy.env.push((key.to_string(), value.to_string()));
+                    Ok(())
+                }
+                "quota" => parse_quota(rest, &mut policy.limits),
+                "net" => {
+                    policy.net.push(parse_net(rest)?);
+                    Ok(())
+                }
+                "raise" => {
+                    policy.raise = parse_raise(rest)?;
+                    Ok(())
+                }
+                _ => Err(format!("unknown directive `{}`", directive)),
+            });
+            parsed.map_err(|msg| error_at(line, msg))?;
+        }
+        Ok(policy)
+    }
+
+    /// A fresh sandbox with this policy's args, env, quotas, network
+    /// allowlist and raise policy, and its preopens open in the fd table.
+    #[flux::trusted]
+    pub fn build(&self) -> Result<VmCtx, PolicyError> {
+        // WASI passes args and env as concatenated NUL-terminated strings
+        let mut arg_buffer = Vec::new();
+        for arg in &self.args {
+            arg_buffer.extend_from_slice(arg.as_bytes());
+            arg_buffer.push(0);
+        }
+        let mut env_buffer = Vec::new();
+        for (key, value) in &self.env {
+            env_buffer.extend_from_slice(format!("{}={}", key, value).as_bytes());
+            env_buffer.push(0);
+        }
+        let mut ctx = VmCtx::new(
+            RVec::from_vec(arg_buffer),
+            self.args.len(),
+            RVec::from_vec(env_buffer),
+            self.env.len(),
+        );
+        ctx.quota = Quota::new(self.limits);
+        ctx.raise_policy = self.raise;
+        for endpoint in &self.net {
+            ctx.netlist.push(*endpoint);
+        }
+        for spec in &self.preopens {
+            let fd = match os_open_dir(spec.host.as_bytes()) {
+                Ok(fd) => fd,
+                Err(e) => {
+                    close_preopens(&ctx);
+                    let msg = format!("cannot open `{}`: {:?}", spec.host, e);
+                    return Err(error_at(spec.line, msg));
+                }
+            };
+            let v_fd = match ctx.create_fd(fd, spec.rights) {
+                Ok(v_fd) => v_fd,
+                Err(e) => {
+                    let _ = os_close(fd);
+                    close_preopens(&ctx);
+                    let msg = format!("cannot preopen `{}`: {:?}", spec.guest, e);
+                    return Err(error_at(spec.line, msg));
+                }
+            };
+            ctx.preopens.push(Preopen {
+                fd: v_fd,
+                name: RVec::from_vec(spec.guest.as_bytes().to_vec()),
+            });
+        }
+        Ok(ctx)
+    }
+}
+
+/// Close the host fds of the preopens a failed `build` already opened.
+/// `VmCtx` has no `Drop`, so nothing else would.
+#[flux::trusted]
+fn close_preopens(ctx: &VmCtx) {
+    let mut idx = 0;
+    while idx < ctx.preopens.len() {
+        if let Ok(entry) = ctx.fdmap.lookup(ctx.preopens[idx].fd) {
+            let _ = os_close(entry.fd);
+        }
+        idx += 1;
+    }
+}
+
+/// Read, parse and apply the policy file at `path`.
+#[flux::trusted]
+pub fn load_policy(path: &Path) -> Result<VmCtx, PolicyError> {
+    let text = std::fs::read_to_string(path).map_err(|e| PolicyError {
+        line: None,
+        msg: format!("cannot read `{}`: {}", path.display(), e),
+    })?;
+    Policy::parse(&text)?.build()
+}
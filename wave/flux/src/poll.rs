@@ -0,0 +1,44 @@
+use crate::layout::{decode_subscription, encode_event, event, subscription};
+use crate::{guard::Access, rvec::RVec, types::*, unwrap_result};
+
+/// Decode `nsubs` WASI `subscription`s starting at `subs`. The whole array is
+/// checked against linear memory and its guards before any of it is read. The
+/// layout holds no pointers, so it is the same for wasm32 and memory64 guests.
+pub fn parse_subscriptions<P: GuestPtr>(
+    ctx: &VmCtx,
+    subs: P,
+    nsubs: u32,
+) -> RuntimeResult<RVec<Subscription>> {
+    let len = (nsubs as usize) * subscription::SIZE;
+    let subs = ctx.guest_buf(subs, len);
+    unwrap_result!(subs);
+    let _r = ctx.check_guards(subs, len, Access::Read);
+    unwrap_result!(_r);
+    let mut i = 0;
+    let mut subscriptions = RVec::new();
+    while i < nsubs {
+        let sub = decode_subscription(ctx, subs + (i as usize) * subscription::SIZE);
+        unwrap_result!(sub);
+        subscriptions.push(sub);
+        i += 1;
+    }
+
+    Ok(subscriptions)
+}
+
+/// Encode `events` as WASI `event`s starting at `out`.
+pub fn write_events<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    out: P,
+    events: &RVec<Event>,
+) -> RuntimeResult<()> {
+    let out = ctx.guest_buf(out, events.len() * event::SIZE);
+    unwrap_result!(out);
+    let mut i = 0;
+    while i < events.len() {
+        let _r = encode_event(ctx, out + i * event::SIZE, &events[i]);
+        unwrap_result!(_r);
+        i += 1;
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use crate::{fdmap::MAX_SBOX_FDS, rvec::RVec, types::*};
+use RuntimeError::*;
+
+/// Resource limits for a single sandbox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaLimits {
+    pub max_open_fds: usize,
+    pub max_bytes_written_per_fd: u64,
+    pub max_bytes_written: u64,
+    pub max_path_resolutions_per_sec: u32,
+    pub max_iovecs: u32,
+}
+
+impl Default for QuotaLimits {
+    /// Only the limits the runtime itself imposes (fd table size, `IOV_MAX`).
+    fn default() -> Self {
+        QuotaLimits {
+            max_open_fds: MAX_SBOX_FDS,
+            max_bytes_written_per_fd: u64::MAX,
+            max_bytes_written: u64::MAX,
+            max_path_resolutions_per_sec: u32::MAX,
+            max_iovecs: 1024,
+        }
+    }
+}
+
+/// Snapshot of a sandbox's resource usage, for reporting by the host.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub open_fds: usize,
+    pub bytes_written: u64,
+    pub path_resolutions: u64,
+}
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+pub struct Quota {
+    pub limits: QuotaLimits,
+    bytes_written: u64,
+    // indexed by sandbox fd
+    fd_bytes_written: RVec<u64>,
+    path_resolutions: u64,
+    // start (monotonic ns) and count of the current one second window
+    window_start: u64,
+    window_resolutions: u32,
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Quota::new(QuotaLimits::default())
+    }
+}
+
+impl Quota {
+    pub fn new(limits: QuotaLimits) -> Quota {
+        Quota {
+            limits,
+            bytes_written: 0,
+            fd_bytes_written: RVec::from_elem_n(0, MAX_SBOX_FDS),
+            path_resolutions: 0,
+            window_start: 0,
+            window_resolutions: 0,
+        }
+    }
+
+    /// Fails with `Emfile` if another fd can't be opened while `open` are.
+    pub fn check_open_fd(&self, open: usize) -> RuntimeResult<()> {
+        if open >= self.limits.max_open_fds {
+            return Err(Emfile);
+        }
+        Ok(())
+    }
+
+    /// Start accounting for a newly created sandbox fd.
+    pub fn reset_fd(&mut self, fd: u32) {
+        let idx = fd as usize;
+        if idx < self.fd_bytes_written.len() {
+            self.fd_bytes_written[idx] = 0;
+        }
+    }
+
+    pub fn bytes_written_to(&self, fd: u32) -> u64 {
+        let idx = fd as usize;
+        if idx < self.fd_bytes_written.len() {
+            self.fd_bytes_written[idx]
+        } else {
+            0
+        }
+    }
+
+    /// Fails with `Edquot` if writing `n` more bytes to `fd` would exceed
+    /// the per-fd or total write quota.
+    pub fn check_write(&self, fd: u32, n: u64) -> RuntimeResult<()> {
+        let fd_total = self.bytes_written_to(fd).checked_add(n);
+        let total = self.bytes_written.checked_add(n);
+        match (fd_total, total) {
+            (Some(f), Some(t))
+                if f <= self.limits.max_bytes_written_per_fd
+                    && t <= self.limits.max_bytes_written =>
+            {
+                Ok(())
+            }
+            _ => Err(Edquot),
+        }
+    }
+
+    pub fn record_write(&mut self, fd: u32, n: u64) {
+        self.bytes_written = self.bytes_written.saturating_add(n);
+        let idx = fd as usize;
+        if idx < self.fd_bytes_written.len() {
+            self.fd_bytes_written[idx] = self.fd_bytes_written[idx].saturating_add(n);
+        }
+    }
+
+    /// Count one path resolution at monotonic time `now` (in ns), failing
+    /// with `Eagain` once this second's budget is spent.
+    pub fn charge_path_resolution(&mut self, now: u64) -> RuntimeResult<()> {
+        if now.saturating_sub(self.window_start) >= NANOS_PER_SEC {
+            self.window_start = now;
+            self.window_resolutions = 0;
+        }
+        if self.window_resolutions >= self.limits.max_path_resolutions_per_sec {
+            return Err(Eagain);
+        }
+        self.window_resolutions += 1;
+        self.path_resolutions += 1;
+        Ok(())
+    }
+
+    /// Fails with `Einval`, like `writev` past `IOV_MAX`, if a call passes
+    /// more than `max_iovecs` iovecs.
+    pub fn check_iovcnt(&self, iovcnt: u32) -> RuntimeResult<()> {
+        if iovcnt > self.limits.max_iovecs {
+            return Err(Einval);
+        }
+        Ok(())
+    }
+}
+
+impl VmCtx {
+    pub fn quota_usage(&self) -> QuotaUsage {
+        QuotaUsage {
+            open_fds: self.fdmap.open_count(),
+            bytes_written: self.quota.bytes_written,
+            path_resolutions: self.quota.path_resolutions,
+        }
+    }
+}
@@ -1,4 +1,11 @@
-use crate::{path_resolution::resolve_path, rvec::RVec, tcb::path::HostPath, types::*};
+use crate::{
+    guard::Access,
+    path_resolution::resolve_path_cached,
+    rvec::RVec,
+    tcb::{os::os_clock_now, path::HostPath},
+    types::*,
+    unwrap_result,
+};
 use RuntimeError::*;
 
 #[flux::alias(type FitsBool(buf, cnt) = bool[0 <= buf && 0 <= cnt && buf <= buf + cnt && buf + cnt < LINEAR_MEM_SIZE])]
@@ -8,6 +15,15 @@ pub type _FitsBool = bool;
 pub type FitsUsize = usize;
 
 impl VmCtx {
+    /// `Exited` once the guest has exited, so that wrappers do nothing more on
+    /// its behalf
+    pub fn ensure_running(&self) -> RuntimeResult<()> {
+        if self.exit_status.is_some() {
+            return Err(Exited);
+        }
+        Ok(())
+    }
+
     /// Check whether sandbox pointer is actually inside the sandbox
     // TODO: can I eliminate this in favor os in_lin_mem_usize?
     #[flux::sig(fn(&VmCtx, ptr:SboxPtr) -> bool[0 <= ptr && ptr < LINEAR_MEM_SIZE])]
@@ -33,13 +49,33 @@ impl VmCtx {
 
     #[flux::sig(fn(&VmCtx, buf:usize, cnt:usize) -> FitsBool[buf, cnt])]
     pub fn fits_in_lin_mem_usize(&self, buf: usize, cnt: usize) -> bool {
-        let total_size = buf + cnt;
+        let total_size = match buf.checked_add(cnt) {
+            Some(total_size) => total_size,
+            None => return false,
+        };
         if total_size >= self.memlen {
             return false;
         }
         self.in_lin_mem_usize(buf) && self.in_lin_mem_usize(cnt) && buf <= buf + cnt
     }
 
+    /// Check whether a buffer given by a memory64 pointer and size is entirely
+    /// within sandbox
+    #[flux::sig(fn(&VmCtx, buf:u64, cnt:u64) -> FitsBool[buf, cnt])]
+    pub fn fits_in_lin_mem_u64(&self, buf: u64, cnt: u64) -> bool {
+        match buf.checked_add(cnt) {
+            Some(total_size) => total_size < self.memlen as u64,
+            None => false,
+        }
+    }
+
+    /// Offset of the `len` byte buffer at guest pointer `ptr`, or `Efault` if
+    /// it is not entirely within the sandbox
+    #[flux::sig(fn(&VmCtx, P, len: usize) -> Result<usize{v: 0 <= v && v <= v + len && v + len < LINEAR_MEM_SIZE}, RuntimeError>)]
+    pub fn guest_buf<P: GuestPtr>(&self, ptr: P, len: usize) -> RuntimeResult<usize> {
+        ptr.widen(self, len)
+    }
+
     /// Copy buffer from sandbox to host
     #[flux::sig(fn(&VmCtx, src:SboxPtr, n:u32{0 <= n && src + n < LINEAR_MEM_SIZE}) -> RVec<u8>[n])]
     pub fn copy_buf_from_sandbox(&self, src: SboxPtr, n: u32) -> RVec<u8> {
@@ -62,8 +98,7 @@ impl VmCtx {
         if src.len() < n as usize || !self.fits_in_lin_mem(dst, n) {
             return Err(Efault);
         }
-        self.memcpy_to_sandbox(dst, src, n);
-        Ok(())
+        self.memcpy_to_sandbox(dst, src, n)
     }
 
     /// Copy arg buffer from from host to sandbox
@@ -73,8 +108,7 @@ impl VmCtx {
             return Err(Efault);
         }
         let arg_buffer = &self.arg_buffer.clone();
-        self.memcpy_to_sandbox(dst, &arg_buffer, n);
-        Ok(())
+        self.memcpy_to_sandbox(dst, &arg_buffer, n)
     }
 
     /// Copy arg buffer from from host to sandbox
@@ -88,13 +122,57 @@ impl VmCtx {
             return Err(Efault);
         }
         let env_buffer = &self.env_buffer.clone();
-        self.memcpy_to_sandbox(dst, &env_buffer, n);
-        Ok(())
+        self.memcpy_to_sandbox(dst, &env_buffer, n)
     }
 
-    #[flux::sig(fn(&VmCtx[@cx], SboxPtr, u32, should_follow:bool, HostFd) -> Result<HostPathSafe[should_follow], RuntimeError>)]
+    /// Copy the `len` byte guest string at `ptr` to the host
+    #[flux::sig(fn(&VmCtx, ptr:SboxPtr, len:u32) -> Result<RVec<u8>[len], RuntimeError>)]
+    pub fn read_guest_bytes(&self, ptr: SboxPtr, len: u32) -> RuntimeResult<RVec<u8>> {
+        if !self.fits_in_lin_mem(ptr, len) {
+            return Err(Efault);
+        }
+        let _r = self.check_access(ptr as usize, len as usize, Access::Read);
+        unwrap_result!(_r);
+        Ok(self.copy_buf_from_sandbox(ptr, len))
+    }
+
+    /// Copy the NUL-terminated guest string at `ptr` to the host, without the
+    /// NUL. At most `max_len` bytes are scanned for the terminator, and never
+    /// any past the end of linear memory.
+    pub fn read_guest_cstr(&self, ptr: SboxPtr, max_len: u32) -> RuntimeResult<RVec<u8>> {
+        let mut len = 0;
+        while len < max_len {
+            let addr = (ptr as usize) + (len as usize);
+            if !self.in_lin_mem_usize(addr) {
+                return Err(Efault);
+            }
+            let _r = self.check_guards(addr, 1, Access::Read);
+            unwrap_result!(_r);
+            if self.mem[addr] == 0 {
+                return self.read_guest_bytes(ptr, len);
+            }
+            len += 1;
+        }
+        Err(Enametoolong)
+    }
+
+    /// `read_guest_bytes`, validated as UTF-8
+    pub fn read_guest_str(&self, ptr: SboxPtr, len: u32) -> RuntimeResult<String> {
+        let bytes = self.read_guest_bytes(ptr, len);
+        unwrap_result!(bytes);
+        utf8_string(bytes)
+    }
+
+    /// `read_guest_cstr`, validated as UTF-8
+    pub fn read_guest_cstr_str(&self, ptr: SboxPtr, max_len: u32) -> RuntimeResult<String> {
+        let bytes = self.read_guest_cstr(ptr, max_len);
+        unwrap_result!(bytes);
+        utf8_string(bytes)
+    }
+
+    #[flux::sig(fn(&mut VmCtx[@cx], SboxPtr, u32, should_follow:bool, HostFd) -> Result<HostPathSafe[should_follow], RuntimeError>)]
     pub fn translate_path(
-        &self,
+        &mut self,
         path: SboxPtr,
         path_len: u32,
         should_follow: bool,
@@ -103,11 +181,25 @@ impl VmCtx {
         if !self.fits_in_lin_mem(path, path_len) {
             return Err(Eoverflow);
         }
+        let _r = self.check_access(path as usize, path_len as usize, Access::Read);
+        unwrap_result!(_r);
+        let now = os_clock_now(ClockId::Monotonic);
+        unwrap_result!(now);
+        let _r = self.quota.charge_path_resolution(now);
+        unwrap_result!(_r);
         let host_buffer = self.copy_buf_from_sandbox(path, path_len);
-        resolve_path(host_buffer, should_follow, dirfd)
+        resolve_path_cached(host_buffer, should_follow, dirfd, &mut self.link_cache)
         // self.resolve_path(host_buffer)
     }
 
+    // The read_* primitives do not consult guards: callers reading on the
+    // guest's behalf go through read_ptr_pair, read_guest_bytes and friends,
+    // which check the whole range first. Writes are always checked.
+    #[flux::sig(fn(&VmCtx, FitsUsize[1]) -> u8)]
+    pub fn read_u8(&self, start: usize) -> u8 {
+        self.mem[start]
+    }
+
     #[flux::sig(fn(&VmCtx, FitsUsize[2]) -> u16)]
     pub fn read_u16(&self, start: usize) -> u16 {
         let bytes: [u8; 2] = [self.mem[start], self.mem[start + 1]];
@@ -150,11 +242,33 @@ impl VmCtx {
         if !self.fits_in_lin_mem_usize(start, 8) {
             return Err(Eoverflow);
         }
+        let _r = self.check_access(start, 8, Access::Read);
+        unwrap_result!(_r);
         let x1 = self.read_u32(start);
         let x2 = self.read_u32(start + 4);
         Ok((x1, x2))
     }
 
+    /// read a pair of guest pointers/sizes, e.g. an iovec, from wasm linear memory
+    pub fn read_ptr_pair<P: GuestPtr>(&self, start: usize) -> RuntimeResult<(u64, u64)> {
+        if !self.fits_in_lin_mem_usize(start, 2 * P::SIZE) {
+            return Err(Eoverflow);
+        }
+        let _r = self.check_access(start, 2 * P::SIZE, Access::Read);
+        unwrap_result!(_r);
+        let x1 = self.read_ptr::<P>(start);
+        unwrap_result!(x1);
+        let x2 = self.read_ptr::<P>(start + P::SIZE);
+        unwrap_result!(x2);
+        Ok((x1, x2))
+    }
+
+    /// read a guest pointer/size from wasm linear memory, widened to u64
+    #[flux::sig(fn(&VmCtx, usize) -> Result<u64, RuntimeError>)]
+    pub fn read_ptr<P: GuestPtr>(&self, start: usize) -> RuntimeResult<u64> {
+        P::read(self, start)
+    }
+
     #[flux::sig(fn(&VmCtx[@cx], &RVec<WasmIoVec>) -> RVec<NativeIoVecOk[cx.base]>)]
     pub fn translate_iovs(&self, iovs: &RVec<WasmIoVec>) -> RVec<NativeIoVec> {
         let mut idx = 0;
@@ -171,8 +285,67 @@ impl VmCtx {
     }
 
     // TODO @cx is redundant here but due to https://github.com/liquid-rust/flux/issues/158
-    #[flux::sig(fn (&mut VmCtx[@cx], FitsUsize[1], v: u8))]
-    pub fn write_u8(&mut self, offset: usize, v: u8) {
+    #[flux::sig(fn (&mut VmCtx[@cx], FitsUsize[1], v: u8) -> Result<(), RuntimeError>)]
+    pub fn write_u8(&mut self, offset: usize, v: u8) -> RuntimeResult<()> {
+        let _r = self.check_guards(offset, 1, Access::Write);
+        unwrap_result!(_r);
         self.mem[offset] = v;
+        self.mark_dirty(offset, 1);
+        Ok(())
     }
+
+    #[flux::sig(fn (&mut VmCtx[@cx], FitsUsize[2], v: u16) -> Result<(), RuntimeError>)]
+    pub fn write_u16(&mut self, start: usize, v: u16) -> RuntimeResult<()> {
+        let _r = self.check_guards(start, 2, Access::Write);
+        unwrap_result!(_r);
+        let bytes: [u8; 2] = v.to_le_bytes();
+        self.mem[start] = bytes[0];
+        self.mem[start + 1] = bytes[1];
+        self.mark_dirty(start, 2);
+        Ok(())
+    }
+
+    /// write u32 to wasm linear memory
+    #[flux::sig(fn (&mut VmCtx[@cx], FitsUsize[4], v: u32) -> Result<(), RuntimeError>)]
+    pub fn write_u32(&mut self, start: usize, v: u32) -> RuntimeResult<()> {
+        let _r = self.check_guards(start, 4, Access::Write);
+        unwrap_result!(_r);
+        let bytes: [u8; 4] = v.to_le_bytes();
+        self.mem[start] = bytes[0];
+        self.mem[start + 1] = bytes[1];
+        self.mem[start + 2] = bytes[2];
+        self.mem[start + 3] = bytes[3];
+        self.mark_dirty(start, 4);
+        Ok(())
+    }
+
+    /// write a guest pointer/size to wasm linear memory, or `Eoverflow` if `v`
+    /// does not fit in a `P`
+    #[flux::sig(fn(&mut VmCtx[@cx], usize, u64) -> Result<(), RuntimeError>)]
+    pub fn write_ptr<P: GuestPtr>(&mut self, start: usize, v: u64) -> RuntimeResult<()> {
+        P::write(self, start, v)
+    }
+
+    /// write u64 to wasm linear memory
+    #[flux::sig(fn (&mut VmCtx[@cx], FitsUsize[8], v: u64) -> Result<(), RuntimeError>)]
+    pub fn write_u64(&mut self, start: usize, v: u64) -> RuntimeResult<()> {
+        let _r = self.check_guards(start, 8, Access::Write);
+        unwrap_result!(_r);
+        let bytes: [u8; 8] = v.to_le_bytes();
+        self.mem[start] = bytes[0];
+        self.mem[start + 1] = bytes[1];
+        self.mem[start + 2] = bytes[2];
+        self.mem[start + 3] = bytes[3];
+        self.mem[start + 4] = bytes[4];
+        self.mem[start + 5] = bytes[5];
+        self.mem[start + 6] = bytes[6];
+        self.mem[start + 7] = bytes[7];
+        self.mark_dirty(start, 8);
+        Ok(())
+    }
+}
+
+#[flux::trusted]
+fn utf8_string(bytes: RVec<u8>) -> RuntimeResult<String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| Eilseq)
 }
@@ -54,6 +54,16 @@ impl<T> RVec<T> {
     pub fn to_vec(self) -> Vec<T> {
         self.inner
     }
+
+    #[flux::trusted]
+    pub fn as_slice(&self) -> &[T] {
+        &self.inner
+    }
+
+    #[flux::trusted]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.inner
+    }
 }
 
 impl<T> std::ops::Index<usize> for RVec<T> {
@@ -0,0 +1,226 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{rvec::RVec, types::*};
+
+/// Granularity of dirty tracking: one wasm page.
+#[flux::constant]
+pub const PAGE_SIZE: usize = 65536;
+
+#[flux::constant]
+pub const NUM_PAGES: usize = (LINEAR_MEM_SIZE + PAGE_SIZE - 1) / PAGE_SIZE;
+
+const WORD_BITS: usize = 64;
+
+/// A set of linear memory pages, one bit per page.
+pub struct PageSet {
+    words: RVec<u64>,
+}
+
+impl Default for PageSet {
+    fn default() -> Self {
+        PageSet::new()
+    }
+}
+
+impl PageSet {
+    pub fn new() -> PageSet {
+        PageSet {
+            words: RVec::from_elem_n(0, NUM_PAGES / WORD_BITS + 1),
+        }
+    }
+
+    pub fn insert(&mut self, page: usize) {
+        let word = page / WORD_BITS;
+        if word < self.words.len() {
+            self.words[word] |= 1 << (page % WORD_BITS);
+        }
+    }
+
+    /// Insert every page overlapping `[start, start + len)`.
+    pub fn insert_range(&mut self, start: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let mut page = start / PAGE_SIZE;
+        let last = (start + len - 1) / PAGE_SIZE;
+        while page <= last {
+            self.insert(page);
+            page += 1;
+        }
+    }
+
+    pub fn contains(&self, page: usize) -> bool {
+        let word = page / WORD_BITS;
+        word < self.words.len() && self.words[word] & (1 << (page % WORD_BITS)) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        let mut count = 0;
+        let mut idx = 0;
+        while idx < self.words.len() {
+            count += self.words[idx].count_ones() as usize;
+            idx += 1;
+        }
+        count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        let mut idx = 0;
+        while idx < self.words.len() {
+            self.words[idx] = 0;
+            idx += 1;
+        }
+    }
+}
+
+/// Which pages of a context's linear memory have been written through the
+/// `VmCtx` write helpers.
+#[derive(Default)]
+pub struct DirtyPages {
+    // every page that may differ from the zeroed initial memory
+    written: PageSet,
+    // pages written since the last snapshot or restore
+    since_checkpoint: PageSet,
+    // id of the snapshot the memory last matched, 0 if none
+    checkpoint: u64,
+}
+
+impl DirtyPages {
+    pub fn mark(&mut self, start: usize, len: usize) {
+        self.written.insert_range(start, len);
+        self.since_checkpoint.insert_range(start, len);
+    }
+}
+
+static NEXT_SNAPSHOT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Saved contents of one linear memory page.
+pub struct SavedPage {
+    pub page: usize,
+    pub data: RVec<u8>,
+}
+
+/// Copy of a context's linear memory, arg/env buffers and exit status. Only
+/// pages that were ever written are stored; every other page is known to be
+/// zero.
+#[flux::refined_by(arg_buf: int, env_buf: int)]
+pub struct Snapshot {
+    id: u64,
+    // sorted by page
+    pages: RVec<SavedPage>,
+    #[flux::field(RVec<u8>[@arg_buf])]
+    arg_buffer: RVec<u8>,
+    #[flux::field(RVec<u8>[@env_buf])]
+    env_buffer: RVec<u8>,
+    #[flux::field(usize{v: v < 1024})]
+    argc: usize,
+    #[flux::field(usize{v: v < 1024})]
+    envc: usize,
+    exit_status: Option<ExitStatus>,
+}
+
+impl Snapshot {
+    /// Number of memory pages stored in the snapshot.
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+/// Bytes of linear memory covered by `page`; the last page is partial.
+#[flux::sig(fn(page: usize) -> usize{v: v <= PAGE_SIZE})]
+pub fn page_len(page: usize) -> usize {
+    let start = page * PAGE_SIZE;
+    if start >= LINEAR_MEM_SIZE {
+        0
+    } else if LINEAR_MEM_SIZE - start < PAGE_SIZE {
+        LINEAR_MEM_SIZE - start
+    } else {
+        PAGE_SIZE
+    }
+}
+
+impl VmCtx {
+    pub fn mark_dirty(&mut self, start: usize, len: usize) {
+        self.dirty_pages.mark(start, len);
+    }
+
+    /// Number of pages written since the last snapshot or restore.
+    pub fn dirty_page_count(&self) -> usize {
+        self.dirty_pages.since_checkpoint.len()
+    }
+
+    #[flux::sig(fn(&mut VmCtx[@cx]) -> Snapshot[cx.arg_buf, cx.env_buf])]
+    pub fn snapshot(&mut self) -> Snapshot {
+        let mut pages = RVec::new();
+        let mut page = 0;
+        while page < NUM_PAGES {
+            if self.dirty_pages.written.contains(page) {
+                pages.push(SavedPage {
+                    page,
+                    data: self.copy_page_from_sandbox(page),
+                });
+            }
+            page += 1;
+        }
+        let id = NEXT_SNAPSHOT_ID.fetch_add(1, Ordering::Relaxed);
+        self.dirty_pages.since_checkpoint.clear();
+        self.dirty_pages.checkpoint = id;
+        Snapshot {
+            id,
+            pages,
+            arg_buffer: self.arg_buffer.clone(),
+            env_buffer: self.env_buffer.clone(),
+            argc: self.argc,
+            envc: self.envc,
+            exit_status: self.exit_status,
+        }
+    }
+
+    /// Restore memory, arg/env buffers and exit status from `snap` and return
+    /// the number of pages rewritten. Restoring the snapshot most recently
+    /// taken or restored only touches the pages written since; any other
+    /// snapshot rewrites every page that may have been written. A guest that
+    /// exited after `snap` was taken can run again.
+    #[flux::sig(fn(self: &strg VmCtx[@cx], &Snapshot[@s]) -> usize
+                ensures self: VmCtx[s.arg_buf, s.env_buf, cx.base])]
+    pub fn restore(&mut self, snap: &Snapshot) -> usize {
+        let full = self.dirty_pages.checkpoint != snap.id;
+        let mut rewritten = 0;
+        let mut cursor = 0;
+        let mut page = 0;
+        while page < NUM_PAGES {
+            let saved = cursor < snap.pages.len() && snap.pages[cursor].page == page;
+            let touch = self.dirty_pages.since_checkpoint.contains(page)
+                || (full && (saved || self.dirty_pages.written.contains(page)));
+            if touch {
+                if saved {
+                    self.copy_page_to_sandbox(page, &snap.pages[cursor].data);
+                } else {
+                    self.zero_page(page);
+                }
+                rewritten += 1;
+            }
+            if saved {
+                self.dirty_pages.written.insert(page);
+                cursor += 1;
+            }
+            page += 1;
+        }
+        self.dirty_pages.since_checkpoint.clear();
+        self.dirty_pages.checkpoint = snap.id;
+
+        self.arg_buffer = snap.arg_buffer.clone();
+        self.env_buffer = snap.env_buffer.clone();
+        self.argc = snap.argc;
+        self.envc = snap.envc;
+        self.exit_status = snap.exit_status;
+        // the host may have reset the sandbox's directories along with its
+        // memory
+        self.link_cache.clear();
+        rewritten
+    }
+}
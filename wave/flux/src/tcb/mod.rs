@@ -1,2 +1,3 @@
+pub mod os;
 pub mod path;
 pub mod sbox_mem;
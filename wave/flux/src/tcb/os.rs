@@ -0,0 +1,468 @@
+//! Thin wrappers around the host system calls used by the wasi layer.
+//! Everything here is trusted: the verified code above only sees `RuntimeResult`s.
+
+use std::ffi::CString;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::rvec::RVec;
+use crate::tcb::path::WasiProto;
+use crate::types::{Advice, ClockId, HostFd, NativeIoVec, RuntimeError, RuntimeResult, Whence};
+use crate::unwrap_result;
+use RuntimeError::*;
+
+fn last_errno() -> i32 {
+    std::io::Error::last_os_error().raw_os_error().unwrap_or(0)
+}
+
+#[flux::trusted]
+pub fn errno_to_runtime_error(errno: i32) -> RuntimeError {
+    match errno {
+        libc::EFAULT => Efault,
+        libc::EOVERFLOW => Eoverflow,
+        libc::ELOOP => Eloop,
+        libc::ENAMETOOLONG => Enametoolong,
+        libc::EBADF => Ebadf,
+        libc::EINVAL => Einval,
+        libc::ESPIPE => Espipe,
+        libc::EMFILE => Emfile,
+        libc::EFBIG => Efbig,
+        libc::ENOSPC => Enospc,
+        libc::EINTR => Eintr,
+        libc::EDQUOT => Edquot,
+        libc::EAGAIN => Eagain,
+        libc::ENOENT => Enoent,
+        libc::ENOTDIR => Enotdir,
+        libc::ENOSYS => Enosys,
+        libc::EILSEQ => Eilseq,
+        libc::ENOTSUP => Enotsup,
+        libc::ECONNREFUSED => Econnrefused,
+        libc::ECONNRESET => Econnreset,
+        libc::EADDRINUSE => Eaddrinuse,
+        libc::ENOTCONN => Enotconn,
+        libc::ENOTSOCK => Enotsock,
+        libc::EPIPE => Epipe,
+        _ => Eio,
+    }
+}
+
+fn host_fd(fd: HostFd) -> libc::c_int {
+    fd.to_raw() as libc::c_int
+}
+
+#[flux::trusted]
+pub fn os_lseek(fd: HostFd, offset: i64, whence: Whence) -> RuntimeResult<u64> {
+    let whence = match whence {
+        Whence::Set => libc::SEEK_SET,
+        Whence::Cur => libc::SEEK_CUR,
+        Whence::End => libc::SEEK_END,
+    };
+    let r = unsafe { libc::lseek(host_fd(fd), offset, whence) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(r as u64)
+}
+
+/// Size in bytes of the file behind `fd`.
+#[flux::trusted]
+pub fn os_file_size(fd: HostFd) -> RuntimeResult<u64> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    let r = unsafe { libc::fstat(host_fd(fd), &mut stat) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(stat.st_size as u64)
+}
+
+/// Device and inode of the file behind `fd`. Unlike the fd number, these
+/// cannot be reused while `fd` is open.
+#[flux::trusted]
+pub fn os_file_id(fd: HostFd) -> RuntimeResult<(u64, u64)> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    let r = unsafe { libc::fstat(host_fd(fd), &mut stat) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok((stat.st_dev, stat.st_ino))
+}
+
+#[flux::trusted]
+pub fn os_fsync(fd: HostFd) -> RuntimeResult<()> {
+    let r = unsafe { libc::fsync(host_fd(fd)) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(())
+}
+
+#[flux::trusted]
+pub fn os_fdatasync(fd: HostFd) -> RuntimeResult<()> {
+    let r = unsafe { libc::fdatasync(host_fd(fd)) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(())
+}
+
+// posix_fallocate and posix_fadvise return the error instead of setting errno
+#[flux::trusted]
+pub fn os_allocate(fd: HostFd, offset: i64, len: i64) -> RuntimeResult<()> {
+    let r = unsafe { libc::posix_fallocate(host_fd(fd), offset, len) };
+    if r != 0 {
+        return Err(errno_to_runtime_error(r));
+    }
+    Ok(())
+}
+
+#[flux::trusted]
+pub fn os_advise(fd: HostFd, offset: i64, len: i64, advice: Advice) -> RuntimeResult<()> {
+    let advice = match advice {
+        Advice::Normal => libc::POSIX_FADV_NORMAL,
+        Advice::Sequential => libc::POSIX_FADV_SEQUENTIAL,
+        Advice::Random => libc::POSIX_FADV_RANDOM,
+        Advice::WillNeed => libc::POSIX_FADV_WILLNEED,
+        Advice::DontNeed => libc::POSIX_FADV_DONTNEED,
+        Advice::NoReuse => libc::POSIX_FADV_NOREUSE,
+    };
+    let r = unsafe { libc::posix_fadvise(host_fd(fd), offset, len, advice) };
+    if r != 0 {
+        return Err(errno_to_runtime_error(r));
+    }
+    Ok(())
+}
+
+/// Write the buffers described by `iovs`, returning the number of bytes written.
+#[flux::trusted]
+pub fn os_writev(fd: HostFd, iovs: &RVec<NativeIoVec>) -> RuntimeResult<usize> {
+    // NativeIoVec is repr(C) with the same layout as iovec
+    let iovs = iovs.as_slice();
+    let r = unsafe {
+        libc::writev(
+            host_fd(fd),
+            iovs.as_ptr() as *const libc::iovec,
+            iovs.len() as libc::c_int,
+        )
+    };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(r as usize)
+}
+
+/// Interest in, and readiness of, a single host fd for `os_poll`.
+#[derive(Clone, Copy, Debug)]
+pub struct PollFd {
+    pub fd: HostFd,
+    pub write: bool,
+    pub ready: bool,
+    pub hangup: bool,
+}
+
+impl PollFd {
+    pub fn new(fd: HostFd, write: bool) -> PollFd {
+        PollFd {
+            fd,
+            write,
+            ready: false,
+            hangup: false,
+        }
+    }
+}
+
+/// Wait until one of `fds` is ready or `timeout_ms` elapses (`-1` waits forever).
+/// Fills in `ready`/`hangup` and returns the number of ready fds.
+#[flux::trusted]
+pub fn os_poll(fds: &mut RVec<PollFd>, timeout_ms: i32) -> RuntimeResult<usize> {
+    let mut host: Vec<libc::pollfd> = fds
+        .as_slice()
+        .iter()
+        .map(|p| libc::pollfd {
+            fd: host_fd(p.fd),
+            events: if p.write { libc::POLLOUT } else { libc::POLLIN },
+            revents: 0,
+        })
+        .collect();
+    let r = unsafe { libc::poll(host.as_mut_ptr(), host.len() as libc::nfds_t, timeout_ms) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    for (p, h) in fds.as_mut_slice().iter_mut().zip(host.iter()) {
+        p.ready = h.revents != 0;
+        p.hangup = h.revents & libc::POLLHUP != 0;
+    }
+    Ok(r as usize)
+}
+
+/// Current time of `id` in nanoseconds.
+#[flux::trusted]
+pub fn os_clock_now(id: ClockId) -> RuntimeResult<u64> {
+    let id = match id {
+        ClockId::Realtime => libc::CLOCK_REALTIME,
+        ClockId::Monotonic => libc::CLOCK_MONOTONIC,
+        ClockId::ProcessCputime => libc::CLOCK_PROCESS_CPUTIME_ID,
+        ClockId::ThreadCputime => libc::CLOCK_THREAD_CPUTIME_ID,
+    };
+    let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+    let r = unsafe { libc::clock_gettime(id, &mut ts) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok((ts.tv_sec as u64) * 1_000_000_000 + (ts.tv_nsec as u64))
+}
+
+/// Number of bytes that can be read from `fd` without blocking.
+#[flux::trusted]
+pub fn os_bytes_readable(fd: HostFd) -> RuntimeResult<u64> {
+    let mut n: libc::c_int = 0;
+    let r = unsafe { libc::ioctl(host_fd(fd), libc::FIONREAD, &mut n) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(n as u64)
+}
+
+fn path_cstr(path: &[u8]) -> RuntimeResult<CString> {
+    CString::new(path).map_err(|_| Einval)
+}
+
+/// Open `path` relative to `fd` as an `O_PATH` fd without following a final
+/// symlink.
+#[flux::trusted]
+pub fn os_open_path(fd: HostFd, path: &[u8]) -> RuntimeResult<HostFd> {
+    let path = path_cstr(path);
+    unwrap_result!(path);
+    let flags = libc::O_PATH | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+    let r = unsafe { libc::openat(host_fd(fd), path.as_ptr(), flags) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(HostFd::from_raw(r as usize))
+}
+
+/// Open the host directory `path` for use as a preopen.
+#[flux::trusted]
+pub fn os_open_dir(path: &[u8]) -> RuntimeResult<HostFd> {
+    let path = path_cstr(path);
+    unwrap_result!(path);
+    let flags = libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC;
+    let r = unsafe { libc::open(path.as_ptr(), flags) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(HostFd::from_raw(r as usize))
+}
+
+// from linux/openat2.h, which libc does not expose
+#[repr(C)]
+struct OpenHow {
+    flags: u64,
+    mode: u64,
+    resolve: u64,
+}
+
+const RESOLVE_NO_MAGICLINKS: u64 = 0x02;
+const RESOLVE_BENEATH: u64 = 0x08;
+
+// set once openat2 has returned ENOSYS, so we stop asking
+static OPENAT2_UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Whether `os_openat2_beneath` can be used on this host.
+pub fn openat2_available() -> bool {
+    !OPENAT2_UNAVAILABLE.load(Ordering::Relaxed)
+}
+
+/// Open `path` relative to `fd` as an `O_PATH` fd, letting the kernel refuse
+/// any resolution that escapes `fd` or crosses a magic link. Fails with
+/// `Enosys` when the host has no `openat2`.
+#[flux::trusted]
+pub fn os_openat2_beneath(fd: HostFd, path: &[u8], should_follow: bool) -> RuntimeResult<HostFd> {
+    if !openat2_available() {
+        return Err(Enosys);
+    }
+    let path = path_cstr(path);
+    unwrap_result!(path);
+    let mut flags = libc::O_PATH | libc::O_CLOEXEC;
+    if !should_follow {
+        flags |= libc::O_NOFOLLOW;
+    }
+    let how = OpenHow {
+        flags: flags as u64,
+        mode: 0,
+        resolve: RESOLVE_BENEATH | RESOLVE_NO_MAGICLINKS,
+    };
+    let r = unsafe {
+        libc::syscall(
+            libc::SYS_openat2,
+            host_fd(fd),
+            path.as_ptr(),
+            &how as *const OpenHow,
+            std::mem::size_of::<OpenHow>(),
+        )
+    };
+    if r < 0 {
+        let errno = last_errno();
+        if errno == libc::ENOSYS {
+            OPENAT2_UNAVAILABLE.store(true, Ordering::Relaxed);
+        }
+        // escapes from RESOLVE_BENEATH are reported as EXDEV
+        if errno == libc::EXDEV {
+            return Err(Enotcapable);
+        }
+        return Err(errno_to_runtime_error(errno));
+    }
+    Ok(HostFd::from_raw(r as usize))
+}
+
+#[flux::trusted]
+pub fn os_sched_yield() -> RuntimeResult<()> {
+    let r = unsafe { libc::sched_yield() };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(())
+}
+
+fn sockaddr_in(addr: u32, port: u16) -> libc::sockaddr_in {
+    let mut sa: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+    sa.sin_family = libc::AF_INET as libc::sa_family_t;
+    sa.sin_port = port.to_be();
+    sa.sin_addr.s_addr = addr.to_be();
+    sa
+}
+
+/// A new IPv4 socket for `proto`.
+#[flux::trusted]
+pub fn os_socket(proto: WasiProto) -> RuntimeResult<HostFd> {
+    let ty = match proto {
+        WasiProto::Tcp => libc::SOCK_STREAM,
+        WasiProto::Udp => libc::SOCK_DGRAM,
+        WasiProto::Unknown => return Err(Einval),
+    };
+    let r = unsafe { libc::socket(libc::AF_INET, ty | libc::SOCK_CLOEXEC, 0) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(HostFd::from_raw(r as usize))
+}
+
+#[flux::trusted]
+pub fn os_connect(fd: HostFd, addr: u32, port: u16) -> RuntimeResult<()> {
+    let sa = sockaddr_in(addr, port);
+    let r = unsafe {
+        libc::connect(
+            host_fd(fd),
+            &sa as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(())
+}
+
+/// Bind `fd` to `addr:port` and start listening on it.
+#[flux::trusted]
+pub fn os_listen(fd: HostFd, addr: u32, port: u16, backlog: u32) -> RuntimeResult<()> {
+    let one: libc::c_int = 1;
+    let sa = sockaddr_in(addr, port);
+    let backlog = backlog.min(libc::c_int::MAX as u32) as libc::c_int;
+    let r = unsafe {
+        libc::setsockopt(
+            host_fd(fd),
+            libc::SOL_SOCKET,
+            libc::SO_REUSEADDR,
+            &one as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    let r = unsafe {
+        libc::bind(
+            host_fd(fd),
+            &sa as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    let r = unsafe { libc::listen(host_fd(fd), backlog) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(())
+}
+
+#[flux::trusted]
+pub fn os_accept(fd: HostFd, nonblock: bool) -> RuntimeResult<HostFd> {
+    let mut flags = libc::SOCK_CLOEXEC;
+    if nonblock {
+        flags |= libc::SOCK_NONBLOCK;
+    }
+    let r = unsafe {
+        libc::accept4(
+            host_fd(fd),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            flags,
+        )
+    };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(HostFd::from_raw(r as usize))
+}
+
+/// Receive into the buffers described by `iovs`. Returns the number of bytes
+/// received and whether a datagram was truncated to fit.
+#[flux::trusted]
+pub fn os_recvmsg(
+    fd: HostFd,
+    iovs: &mut RVec<NativeIoVec>,
+    peek: bool,
+    waitall: bool,
+) -> RuntimeResult<(usize, bool)> {
+    let iovs = iovs.as_mut_slice();
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = iovs.as_mut_ptr() as *mut libc::iovec;
+    msg.msg_iovlen = iovs.len();
+    let mut flags = 0;
+    if peek {
+        flags |= libc::MSG_PEEK;
+    }
+    if waitall {
+        flags |= libc::MSG_WAITALL;
+    }
+    let r = unsafe { libc::recvmsg(host_fd(fd), &mut msg, flags) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok((r as usize, msg.msg_flags & libc::MSG_TRUNC != 0))
+}
+
+/// Send the buffers described by `iovs`. A closed peer is reported as
+/// `Epipe` rather than a `SIGPIPE`.
+#[flux::trusted]
+pub fn os_sendmsg(fd: HostFd, iovs: &RVec<NativeIoVec>) -> RuntimeResult<usize> {
+    let iovs = iovs.as_slice();
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = iovs.as_ptr() as *mut libc::iovec;
+    msg.msg_iovlen = iovs.len();
+    let r = unsafe { libc::sendmsg(host_fd(fd), &msg, libc::MSG_NOSIGNAL) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(r as usize)
+}
+
+#[flux::trusted]
+pub fn os_close(fd: HostFd) -> RuntimeResult<()> {
+    let r = unsafe { libc::close(host_fd(fd)) };
+    if r < 0 {
+        return Err(errno_to_runtime_error(last_errno()));
+    }
+    Ok(())
+}
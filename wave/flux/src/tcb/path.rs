@@ -2,10 +2,14 @@ use std::path::PathBuf;
 
 use owned_components::{readlinkat, OwnedComponent, OwnedComponents};
 
-use crate::{rvec::RVec, types::HostFd};
+use crate::{
+    link_cache::{CachedLink, LinkCache},
+    rvec::RVec,
+    types::HostFd,
+};
 
 #[flux::constant]
-const DEPTH_ERR: isize = i32::MIN as isize;
+pub const DEPTH_ERR: isize = i32::MIN as isize;
 
 #[allow(dead_code)]
 #[flux::opaque]
@@ -14,6 +18,19 @@ pub struct HostPath {
     inner: [u8; crate::types::PATH_MAX],
 }
 
+impl HostPath {
+    /// The path bytes, without the trailing NUL padding.
+    #[flux::trusted]
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = self
+            .inner
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(self.inner.len());
+        &self.inner[..len]
+    }
+}
+
 #[flux::opaque]
 #[flux::refined_by(size:int, ns_prefix:int, depth:int, is_relative:bool)]
 pub struct FOwnedComponents {
@@ -59,6 +76,11 @@ impl FOwnedComponents {
         self.inner.push(value);
     }
 
+    #[flux::trusted]
+    pub fn as_pathbuf(&self) -> PathBuf {
+        self.inner.as_pathbuf()
+    }
+
     #[flux::trusted]
     #[flux::sig(fn (oc:FOwnedComponents) -> Option<HostPathOc[oc]>)]
     pub fn unparse(self) -> Option<HostPath> {
@@ -78,15 +100,14 @@ pub fn get_components(path: &PathBuf) -> RVec<OwnedComponent> {
     components
 }
 
-// #[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct NetEndpoint {
     pub protocol: WasiProto,
     pub addr: u32,
     pub port: u32,
 }
 
-// #[derive(Clone, Copy, PartialEq, Eq)]
-
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum WasiProto {
     Unknown,
     Tcp,
@@ -132,28 +153,58 @@ pub fn min_depth(components: &FOwnedComponents) -> isize {
     curr_depth
 }
 
+// Trusted: readlinkat is outside flux. The answer may come from `cache`
+// instead of a syscall, so `ns_prefix == size` on the result is only as good
+// as the cache: it holds as long as nothing has removed, renamed or replaced
+// a cached prefix under the current directory since it was cached. That is
+// the freshness contract `LinkCache` documents, and the wrappers listed there
+// must keep it by calling `LinkCache::clear`.
 #[flux::trusted]
-#[flux::sig(fn (HostFd, &FOwnedComponents[@oc]) -> Option<{FOwnedComponents: oc.ns_prefix == oc.size}>)]
-fn read_linkat_h(dirfd: HostFd, out_path: &FOwnedComponents) -> Option<FOwnedComponents> {
-    let inner = readlinkat(dirfd.to_raw(), &out_path.inner.as_pathbuf())
-        .ok()
-        .map(|p| OwnedComponents::parse(p))?;
-    Some(FOwnedComponents { inner })
+#[flux::sig(fn (HostFd, &FOwnedComponents[@oc], &mut LinkCache) -> Option<{FOwnedComponents: oc.ns_prefix == oc.size}>)]
+fn read_linkat_h(
+    dirfd: HostFd,
+    out_path: &FOwnedComponents,
+    cache: &mut LinkCache,
+) -> Option<FOwnedComponents> {
+    let prefix = out_path.inner.as_pathbuf();
+    let link = match cache.lookup(&prefix) {
+        Some(link) => link,
+        None => match readlinkat(dirfd.to_raw(), &prefix) {
+            Ok(target) => {
+                let link = CachedLink::Link(target);
+                cache.insert(prefix, link.clone());
+                link
+            }
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+                cache.insert(prefix, CachedLink::NotALink);
+                CachedLink::NotALink
+            }
+            // missing or unreadable: not a symlink for now, but not cached
+            Err(_) => CachedLink::NotALink,
+        },
+    };
+    match link {
+        CachedLink::Link(target) => Some(FOwnedComponents {
+            inner: OwnedComponents::parse(target),
+        }),
+        CachedLink::NotALink => None,
+    }
 }
 
 // Looks at a single component of a path:
 // if it is a symlink, return the linkpath.
 // else, we just append the value to out_path
 #[flux::trusted]
-#[flux::sig(fn (HostFd, &mut NoSymLinks, OwnedComponent, &mut isize) -> Option<FOwnedComponents>)]
+#[flux::sig(fn (HostFd, &mut NoSymLinks, OwnedComponent, &mut isize, &mut LinkCache) -> Option<FOwnedComponents>)]
 pub fn maybe_expand_component(
     dirfd: HostFd,
     out_path: &mut FOwnedComponents,
     comp: OwnedComponent,
     num_symlinks: &mut isize,
+    cache: &mut LinkCache,
 ) -> Option<FOwnedComponents> {
     out_path.inner.push(comp);
-    if let Some(linkpath) = read_linkat_h(dirfd, out_path) {
+    if let Some(linkpath) = read_linkat_h(dirfd, out_path, cache) {
         out_path.inner.pop(); // pop the component we just added, since it is a symlink
         *num_symlinks += 1;
         return Some(linkpath);
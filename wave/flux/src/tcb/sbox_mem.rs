@@ -1,28 +1,88 @@
+use crate::fdmap::FdMap;
+use crate::guard::{Access, Guards};
+use crate::link_cache::LinkCache;
+use crate::quota::Quota;
 use crate::rvec::RVec;
-use crate::types::{NativeIoVec, SboxPtr, VmCtx, WasmIoVec};
+use crate::snapshot::{page_len, DirtyPages, PAGE_SIZE};
+use crate::types::{
+    NativeIoVec, RaisePolicy, RuntimeResult, SboxPtr, VmCtx, WasmIoVec, LINEAR_MEM_SIZE,
+};
+use crate::unwrap_result;
 
 impl VmCtx {
+    /// Allocate a zeroed linear memory and build a context around it.
+    #[flux::trusted]
+    #[flux::sig(fn(arg_buffer: RVec<u8>[@arg_buf], argc: usize{argc < 1024}, env_buffer: RVec<u8>[@env_buf], envc: usize{envc < 1024})
+                -> VmCtx{cx: cx.arg_buf == arg_buf && cx.env_buf == env_buf})]
+    pub fn new(arg_buffer: RVec<u8>, argc: usize, env_buffer: RVec<u8>, envc: usize) -> VmCtx {
+        let mem = RVec::from_elem_n(0, LINEAR_MEM_SIZE);
+        let raw = mem.as_slice().as_ptr() as usize;
+        VmCtx {
+            raw,
+            mem,
+            memlen: LINEAR_MEM_SIZE,
+            arg_buffer,
+            env_buffer,
+            envc,
+            argc,
+            fdmap: FdMap::new(),
+            preopens: RVec::new(),
+            netlist: RVec::new(),
+            quota: Quota::default(),
+            guards: Guards::new(),
+            link_cache: LinkCache::default(),
+            raise_policy: RaisePolicy::default(),
+            exit_status: None,
+            dirty_pages: DirtyPages::default(),
+        }
+    }
+
     #[flux::sig(fn(&VmCtx[@cx], WasmIoVec) -> NativeIoVecOk[cx.base])]
     pub fn translate_iov(&self, iov: WasmIoVec) -> NativeIoVec {
-        let swizzled_base = self.raw + iov.iov_base as usize;
+        let swizzled_base = self.raw + iov.iov_base;
         NativeIoVec {
             iov_base: swizzled_base,
-            iov_len: iov.iov_len as usize,
+            iov_len: iov.iov_len,
         }
     }
 
     // FLUX-TODO: capacity
     #[flux::trusted]
     #[flux::sig(fn(&VmCtx, &mut RVec<u8>[n], src: SboxPtr{src + n < LINEAR_MEM_SIZE}, n:u32{0 <= n}))]
-    #[allow(unused_variables)]
     pub fn memcpy_from_sandbox(&self, dst: &mut RVec<u8>, src: SboxPtr, n: u32) {
-        todo!()
+        let (src, n) = (src as usize, n as usize);
+        dst.as_mut_slice()[..n].copy_from_slice(&self.mem.as_slice()[src..src + n]);
+    }
+
+    #[flux::trusted]
+    #[flux::sig(fn(&mut VmCtx[@cx], dst: SboxPtr{dst + n < LINEAR_MEM_SIZE}, &RVec<u8>{sz:n <= sz}, n:u32) -> Result<(), RuntimeError>)]
+    pub fn memcpy_to_sandbox(&mut self, dst: SboxPtr, src: &RVec<u8>, n: u32) -> RuntimeResult<()> {
+        let (dst, n) = (dst as usize, n as usize);
+        let _r = self.check_guards(dst, n, Access::Write);
+        unwrap_result!(_r);
+        self.mem.as_mut_slice()[dst..dst + n].copy_from_slice(&src.as_slice()[..n]);
+        self.mark_dirty(dst, n);
+        Ok(())
+    }
+
+    #[flux::trusted]
+    pub fn copy_page_from_sandbox(&self, page: usize) -> RVec<u8> {
+        let start = page * PAGE_SIZE;
+        RVec::from_vec(self.mem.as_slice()[start..start + page_len(page)].to_vec())
+    }
+
+    // Does not mark the page dirty: only used to restore a snapshot
+    #[flux::trusted]
+    pub fn copy_page_to_sandbox(&mut self, page: usize, src: &RVec<u8>) {
+        let start = page * PAGE_SIZE;
+        let len = page_len(page);
+        self.mem.as_mut_slice()[start..start + len].copy_from_slice(&src.as_slice()[..len]);
     }
 
-    #[allow(unused_variables)]
     #[flux::trusted]
-    #[flux::sig(fn(&mut VmCtx[@cx], dst: SboxPtr{dst + n < LINEAR_MEM_SIZE}, &RVec<u8>{sz:n <= sz}, n:u32))]
-    pub fn memcpy_to_sandbox(&mut self, dst: SboxPtr, src: &RVec<u8>, n: u32) {
-        todo!()
+    pub fn zero_page(&mut self, page: usize) {
+        let start = page * PAGE_SIZE;
+        let len = page_len(page);
+        self.mem.as_mut_slice()[start..start + len].fill(0);
     }
 }
@@ -1,4 +1,11 @@
+use crate::fdmap::{FdMap, Preopen};
+use crate::guard::Guards;
+use crate::link_cache::LinkCache;
+use crate::quota::Quota;
 use crate::rvec::RVec;
+use crate::snapshot::DirtyPages;
+use crate::tcb::path::NetEndpoint;
+use RuntimeError::*;
 
 #[flux::constant]
 pub const LINEAR_MEM_SIZE: usize = 4294965096; //4GB
@@ -14,6 +21,90 @@ pub type RuntimeResult<T> = Result<T, RuntimeError>;
 pub type SboxPtr = u32;
 pub type HostPtr = usize;
 
+/// Width of guest pointers and sizes in linear memory: `u32` for wasm32
+/// guests and `u64` for memory64 guests.
+///
+/// The width-specific code lives in the two impls, so flux checks each of
+/// them against the signatures here.
+pub trait GuestPtr: Copy + Into<u64> {
+    /// Size in bytes of a pointer or `size` in guest memory.
+    const SIZE: usize;
+
+    /// Offset of the `len` byte buffer at this pointer, or `Efault` if it is
+    /// not entirely within the sandbox.
+    #[flux::sig(fn(Self, &VmCtx, len: usize) -> Result<usize{v: 0 <= v && v <= v + len && v + len < LINEAR_MEM_SIZE}, RuntimeError>)]
+    fn widen(self, ctx: &VmCtx, len: usize) -> RuntimeResult<usize>;
+
+    /// Read a pointer/size of this width at `start`, widened to u64.
+    #[flux::sig(fn(&VmCtx, usize) -> Result<u64, RuntimeError>)]
+    fn read(ctx: &VmCtx, start: usize) -> RuntimeResult<u64>;
+
+    /// Write `v` as a pointer/size of this width at `start`, or `Eoverflow` if
+    /// it does not fit in this width.
+    #[flux::sig(fn(&mut VmCtx[@cx], usize, u64) -> Result<(), RuntimeError>)]
+    fn write(ctx: &mut VmCtx, start: usize, v: u64) -> RuntimeResult<()>;
+}
+
+impl GuestPtr for u32 {
+    const SIZE: usize = 4;
+
+    #[flux::sig(fn(u32, &VmCtx, len: usize) -> Result<usize{v: 0 <= v && v <= v + len && v + len < LINEAR_MEM_SIZE}, RuntimeError>)]
+    fn widen(self, ctx: &VmCtx, len: usize) -> RuntimeResult<usize> {
+        if !ctx.fits_in_lin_mem_usize(self as usize, len) {
+            return Err(Efault);
+        }
+        Ok(self as usize)
+    }
+
+    #[flux::sig(fn(&VmCtx, usize) -> Result<u64, RuntimeError>)]
+    fn read(ctx: &VmCtx, start: usize) -> RuntimeResult<u64> {
+        if !ctx.fits_in_lin_mem_usize(start, 4) {
+            return Err(Eoverflow);
+        }
+        Ok(ctx.read_u32(start) as u64)
+    }
+
+    #[flux::sig(fn(&mut VmCtx[@cx], usize, u64) -> Result<(), RuntimeError>)]
+    fn write(ctx: &mut VmCtx, start: usize, v: u64) -> RuntimeResult<()> {
+        if !ctx.fits_in_lin_mem_usize(start, 4) {
+            return Err(Efault);
+        }
+        if v > u32::MAX as u64 {
+            return Err(Eoverflow);
+        }
+        ctx.write_u32(start, v as u32)
+    }
+}
+
+impl GuestPtr for u64 {
+    const SIZE: usize = 8;
+
+    #[flux::sig(fn(u64, &VmCtx, len: usize) -> Result<usize{v: 0 <= v && v <= v + len && v + len < LINEAR_MEM_SIZE}, RuntimeError>)]
+    fn widen(self, ctx: &VmCtx, len: usize) -> RuntimeResult<usize> {
+        if !ctx.fits_in_lin_mem_u64(self, len as u64) {
+            return Err(Efault);
+        }
+        Ok(self as usize)
+    }
+
+    #[flux::sig(fn(&VmCtx, usize) -> Result<u64, RuntimeError>)]
+    fn read(ctx: &VmCtx, start: usize) -> RuntimeResult<u64> {
+        if !ctx.fits_in_lin_mem_usize(start, 8) {
+            return Err(Eoverflow);
+        }
+        Ok(ctx.read_u64(start))
+    }
+
+    #[flux::sig(fn(&mut VmCtx[@cx], usize, u64) -> Result<(), RuntimeError>)]
+    fn write(ctx: &mut VmCtx, start: usize, v: u64) -> RuntimeResult<()> {
+        if !ctx.fits_in_lin_mem_usize(start, 8) {
+            return Err(Efault);
+        }
+        ctx.write_u64(start, v)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RuntimeError {
     Success = 0,
     Efault,
@@ -21,19 +112,445 @@ pub enum RuntimeError {
     Eloop,
     Enotcapable,
     Enametoolong,
+    Ebadf,
+    Einval,
+    Espipe,
+    Emfile,
+    Efbig,
+    Enospc,
+    Eio,
+    Eintr,
+    Edquot,
+    Eagain,
+    Enoent,
+    Enotdir,
+    Enosys,
+    Eilseq,
+    Enotsup,
+    Econnrefused,
+    Econnreset,
+    Eaddrinuse,
+    Enotconn,
+    Enotsock,
+    Epipe,
+    /// Not an errno: the guest called `proc_exit` or was terminated by
+    /// `proc_raise`, and must not be resumed. The status is in
+    /// `VmCtx::exit_status`.
+    Exited,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+impl RuntimeError {
+    /// The WASI preview1 `errno` value for this error.
+    pub fn to_wasi_errno(self) -> u16 {
+        match self {
+            Success => 0,
+            Efault => 21,
+            Eoverflow => 61,
+            Eloop => 32,
+            Enotcapable => 76,
+            Enametoolong => 37,
+            Ebadf => 8,
+            Einval => 28,
+            Espipe => 70,
+            Emfile => 33,
+            Efbig => 22,
+            Enospc => 51,
+            Eio => 29,
+            Eintr => 27,
+            Edquot => 19,
+            Eagain => 6,
+            Enoent => 44,
+            Enotdir => 54,
+            Enosys => 52,
+            Eilseq => 25,
+            Enotsup => 58,
+            Econnrefused => 14,
+            Econnreset => 15,
+            Eaddrinuse => 3,
+            Enotconn => 53,
+            Enotsock => 57,
+            Epipe => 64,
+            // only seen if an embedder resumes an exited guest anyway
+            Exited => 11,
+        }
+    }
+
+    /// The error for a WASI preview1 `errno` value wave can produce.
+    pub fn from_wasi_errno(errno: u16) -> Option<RuntimeError> {
+        let all = [
+            Success,
+            Efault,
+            Eoverflow,
+            Eloop,
+            Enotcapable,
+            Enametoolong,
+            Ebadf,
+            Einval,
+            Espipe,
+            Emfile,
+            Efbig,
+            Enospc,
+            Eio,
+            Eintr,
+            Edquot,
+            Eagain,
+            Enoent,
+            Enotdir,
+            Enosys,
+            Eilseq,
+            Enotsup,
+            Econnrefused,
+            Econnreset,
+            Eaddrinuse,
+            Enotconn,
+            Enotsock,
+            Epipe,
+        ];
+        let mut idx = 0;
+        while idx < all.len() {
+            if all[idx].to_wasi_errno() == errno {
+                return Some(all[idx]);
+            }
+            idx += 1;
+        }
+        None
+    }
+}
+
+/// Reference point for `fd_seek`, numbered as in WASI `whence`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Whence {
+    Set,
+    Cur,
+    End,
+}
+
+impl Whence {
+    pub fn from_u32(v: u32) -> Option<Whence> {
+        match v {
+            0 => Some(Whence::Set),
+            1 => Some(Whence::Cur),
+            2 => Some(Whence::End),
+            _ => None,
+        }
+    }
+}
+
+/// File access pattern hint for `fd_advise`, numbered as in WASI `advice`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Advice {
+    Normal,
+    Sequential,
+    Random,
+    WillNeed,
+    DontNeed,
+    NoReuse,
+}
+
+impl Advice {
+    pub fn from_u32(v: u32) -> Option<Advice> {
+        match v {
+            0 => Some(Advice::Normal),
+            1 => Some(Advice::Sequential),
+            2 => Some(Advice::Random),
+            3 => Some(Advice::WillNeed),
+            4 => Some(Advice::DontNeed),
+            5 => Some(Advice::NoReuse),
+            _ => None,
+        }
+    }
+}
+
+/// How a guest stopped running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// `proc_exit` with this code.
+    Exited(u32),
+    /// `proc_raise` with this WASI signal, under `RaisePolicy::Terminate`.
+    Signaled(u8),
+}
+
+impl ExitStatus {
+    /// Process exit code for the embedder, using the shell's `128 + signal`
+    /// convention for signals.
+    pub fn code(self) -> u32 {
+        match self {
+            ExitStatus::Exited(code) => code,
+            ExitStatus::Signaled(sig) => 128 + sig as u32,
+        }
+    }
+}
+
+/// What `proc_raise` does. Signals are never delivered to the host process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RaisePolicy {
+    /// Fail with `Enotsup`.
+    #[default]
+    Deny,
+    /// Succeed without doing anything.
+    Ignore,
+    /// Stop the guest as if it had been killed by the signal.
+    Terminate,
+}
+
+/// Highest WASI `signal` number (`sys`).
+pub const MAX_SIGNAL: u8 = 30;
+
+/// Clock used by a clock subscription, numbered as in WASI `clockid`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockId {
+    Realtime,
+    Monotonic,
+    ProcessCputime,
+    ThreadCputime,
+}
+
+impl ClockId {
+    pub fn from_u32(v: u32) -> Option<ClockId> {
+        match v {
+            0 => Some(ClockId::Realtime),
+            1 => Some(ClockId::Monotonic),
+            2 => Some(ClockId::ProcessCputime),
+            3 => Some(ClockId::ThreadCputime),
+            _ => None,
+        }
+    }
+}
+
+/// Type of a file, numbered as in WASI `filetype`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filetype {
+    Unknown = 0,
+    BlockDevice = 1,
+    CharacterDevice = 2,
+    Directory = 3,
+    RegularFile = 4,
+    SocketDgram = 5,
+    SocketStream = 6,
+    SymbolicLink = 7,
+}
+
+impl Filetype {
+    pub fn from_u8(v: u8) -> Option<Filetype> {
+        match v {
+            0 => Some(Filetype::Unknown),
+            1 => Some(Filetype::BlockDevice),
+            2 => Some(Filetype::CharacterDevice),
+            3 => Some(Filetype::Directory),
+            4 => Some(Filetype::RegularFile),
+            5 => Some(Filetype::SocketDgram),
+            6 => Some(Filetype::SocketStream),
+            7 => Some(Filetype::SymbolicLink),
+            _ => None,
+        }
+    }
+}
+
+/// A WASI `prestat`. Every preopen is a directory, so only the length of its
+/// name is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Prestat {
+    pub name_len: u32,
+}
+
+/// A WASI `fdstat`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fdstat {
+    pub filetype: Filetype,
+    /// WASI `fdflags` bits.
+    pub flags: u16,
+    pub rights_base: Rights,
+    pub rights_inheriting: Rights,
+}
+
+/// A WASI `filestat`. Times are nanoseconds since the epoch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Filestat {
+    pub dev: u64,
+    pub ino: u64,
+    pub filetype: Filetype,
+    pub nlink: u64,
+    pub size: u64,
+    pub atim: u64,
+    pub mtim: u64,
+    pub ctim: u64,
+}
+
+/// A WASI `dirent` header; the name follows it in the buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dirent {
+    /// Cookie of the next entry.
+    pub next: u64,
+    pub ino: u64,
+    pub namlen: u32,
+    pub filetype: Filetype,
+}
+
+/// A decoded WASI `subscription`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subscription {
+    pub userdata: u64,
+    pub kind: SubscriptionKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    /// `id` is kept raw so an unknown clock is reported in its event
+    /// instead of failing the whole call.
+    Clock {
+        id: u32,
+        timeout: u64,
+        precision: u64,
+        abstime: bool,
+    },
+    FdRead(u32),
+    FdWrite(u32),
+}
+
+/// Event type tag, numbered as in WASI `eventtype`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventType {
+    Clock = 0,
+    FdRead = 1,
+    FdWrite = 2,
+}
+
+impl EventType {
+    pub fn from_u8(v: u8) -> Option<EventType> {
+        match v {
+            0 => Some(EventType::Clock),
+            1 => Some(EventType::FdRead),
+            2 => Some(EventType::FdWrite),
+            _ => None,
+        }
+    }
+}
+
+/// A WASI `event` to be written back to the sandbox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub userdata: u64,
+    pub error: RuntimeError,
+    pub ty: EventType,
+    pub nbytes: u64,
+    pub hangup: bool,
+}
+
+/// WASI rights attached to a sandbox fd. Bits follow the preview1 `rights` flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rights(u64);
+
+impl Rights {
+    pub const FD_DATASYNC: Rights = Rights(1 << 0);
+    pub const FD_READ: Rights = Rights(1 << 1);
+    pub const FD_SEEK: Rights = Rights(1 << 2);
+    pub const FD_FDSTAT_SET_FLAGS: Rights = Rights(1 << 3);
+    pub const FD_SYNC: Rights = Rights(1 << 4);
+    pub const FD_TELL: Rights = Rights(1 << 5);
+    pub const FD_WRITE: Rights = Rights(1 << 6);
+    pub const FD_ADVISE: Rights = Rights(1 << 7);
+    pub const FD_ALLOCATE: Rights = Rights(1 << 8);
+    pub const PATH_CREATE_DIRECTORY: Rights = Rights(1 << 9);
+    pub const PATH_CREATE_FILE: Rights = Rights(1 << 10);
+    pub const PATH_LINK_SOURCE: Rights = Rights(1 << 11);
+    pub const PATH_LINK_TARGET: Rights = Rights(1 << 12);
+    pub const PATH_OPEN: Rights = Rights(1 << 13);
+    pub const FD_READDIR: Rights = Rights(1 << 14);
+    pub const PATH_READLINK: Rights = Rights(1 << 15);
+    pub const PATH_RENAME_SOURCE: Rights = Rights(1 << 16);
+    pub const PATH_RENAME_TARGET: Rights = Rights(1 << 17);
+    pub const PATH_FILESTAT_GET: Rights = Rights(1 << 18);
+    pub const PATH_FILESTAT_SET_SIZE: Rights = Rights(1 << 19);
+    pub const PATH_FILESTAT_SET_TIMES: Rights = Rights(1 << 20);
+    pub const FD_FILESTAT_GET: Rights = Rights(1 << 21);
+    pub const FD_FILESTAT_SET_SIZE: Rights = Rights(1 << 22);
+    pub const FD_FILESTAT_SET_TIMES: Rights = Rights(1 << 23);
+    pub const PATH_SYMLINK: Rights = Rights(1 << 24);
+    pub const PATH_REMOVE_DIRECTORY: Rights = Rights(1 << 25);
+    pub const PATH_UNLINK_FILE: Rights = Rights(1 << 26);
+    pub const POLL_FD_READWRITE: Rights = Rights(1 << 27);
+    pub const SOCK_SHUTDOWN: Rights = Rights(1 << 28);
+    pub const SOCK_ACCEPT: Rights = Rights(1 << 29);
+
+    pub const fn empty() -> Rights {
+        Rights(0)
+    }
+
+    pub const fn all() -> Rights {
+        Rights((1 << 30) - 1)
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u64) -> Rights {
+        Rights(bits & Rights::all().0)
+    }
+
+    pub const fn contains(self, other: Rights) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The right with WASI name `name`, e.g. `fd_read`.
+    pub fn from_name(name: &str) -> Option<Rights> {
+        let right = match name {
+            "fd_datasync" => Rights::FD_DATASYNC,
+            "fd_read" => Rights::FD_READ,
+            "fd_seek" => Rights::FD_SEEK,
+            "fd_fdstat_set_flags" => Rights::FD_FDSTAT_SET_FLAGS,
+            "fd_sync" => Rights::FD_SYNC,
+            "fd_tell" => Rights::FD_TELL,
+            "fd_write" => Rights::FD_WRITE,
+            "fd_advise" => Rights::FD_ADVISE,
+            "fd_allocate" => Rights::FD_ALLOCATE,
+            "path_create_directory" => Rights::PATH_CREATE_DIRECTORY,
+            "path_create_file" => Rights::PATH_CREATE_FILE,
+            "path_link_source" => Rights::PATH_LINK_SOURCE,
+            "path_link_target" => Rights::PATH_LINK_TARGET,
+            "path_open" => Rights::PATH_OPEN,
+            "fd_readdir" => Rights::FD_READDIR,
+            "path_readlink" => Rights::PATH_READLINK,
+            "path_rename_source" => Rights::PATH_RENAME_SOURCE,
+            "path_rename_target" => Rights::PATH_RENAME_TARGET,
+            "path_filestat_get" => Rights::PATH_FILESTAT_GET,
+            "path_filestat_set_size" => Rights::PATH_FILESTAT_SET_SIZE,
+            "path_filestat_set_times" => Rights::PATH_FILESTAT_SET_TIMES,
+            "fd_filestat_get" => Rights::FD_FILESTAT_GET,
+            "fd_filestat_set_size" => Rights::FD_FILESTAT_SET_SIZE,
+            "fd_filestat_set_times" => Rights::FD_FILESTAT_SET_TIMES,
+            "path_symlink" => Rights::PATH_SYMLINK,
+            "path_remove_directory" => Rights::PATH_REMOVE_DIRECTORY,
+            "path_unlink_file" => Rights::PATH_UNLINK_FILE,
+            "poll_fd_readwrite" => Rights::POLL_FD_READWRITE,
+            "sock_shutdown" => Rights::SOCK_SHUTDOWN,
+            "sock_accept" => Rights::SOCK_ACCEPT,
+            _ => return None,
+        };
+        Some(right)
+    }
+}
+
+impl std::ops::BitOr for Rights {
+    type Output = Rights;
+
+    fn bitor(self, rhs: Rights) -> Rights {
+        Rights(self.0 | rhs.0)
+    }
+}
+
+// Decoded from either the wasm32 (8 byte) or memory64 (16 byte) iovec layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[flux::refined_by(iov_base: int)]
 pub struct WasmIoVec {
-    #[flux::field({ u32[@iov_base] : 0 <= iov_base})]
-    pub iov_base: u32,
-    #[flux::field(u32{ len : 0 <= len && iov_base <= iov_base + len && iov_base + len < LINEAR_MEM_SIZE })]
-    pub iov_len: u32,
+    #[flux::field({ usize[@iov_base] : 0 <= iov_base})]
+    pub iov_base: usize,
+    #[flux::field(usize{ len : 0 <= len && iov_base <= iov_base + len && iov_base + len < LINEAR_MEM_SIZE })]
+    pub iov_len: usize,
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[flux::refined_by(iov_base: int, iov_len: int)]
 pub struct NativeIoVec {
     #[flux::field(usize[@iov_base])]
@@ -78,18 +595,27 @@ pub struct VmCtx {
     pub envc: usize,
     #[flux::field(usize{v: v < 1024})]
     pub argc: usize,
+    pub fdmap: FdMap,
+    pub preopens: RVec<Preopen>,
+    pub netlist: RVec<NetEndpoint>,
+    pub quota: Quota,
+    pub guards: Guards,
+    pub link_cache: LinkCache,
+    pub raise_policy: RaisePolicy,
+    /// Set once the guest has exited; see `RuntimeError::Exited`.
+    pub exit_status: Option<ExitStatus>,
+    pub dirty_pages: DirtyPages,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct HostFd(usize);
 
 impl HostFd {
-    pub(crate) fn to_raw(&self) -> usize {
+    pub fn to_raw(&self) -> usize {
         self.0
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_raw(w: usize) -> HostFd {
+    pub fn from_raw(w: usize) -> HostFd {
         HostFd(w)
     }
 }
@@ -0,0 +1,541 @@
+use crate::guard::Access;
+use crate::iov::{check_iovs, iovs_len, mark_iovs_dirty, parse_iovs};
+use crate::layout::event;
+use crate::poll::{parse_subscriptions, write_events};
+use crate::tcb::path::{NetEndpoint, WasiProto};
+use crate::{rvec::RVec, tcb::os::*, types::*, unwrap_result};
+use RuntimeError::*;
+
+/// Absolute file offset `base + delta`, or an error if it would land before
+/// the start of the file or outside the range of `i64`.
+fn seek_target(base: u64, delta: i64) -> RuntimeResult<i64> {
+    if base > i64::MAX as u64 {
+        return Err(Eoverflow);
+    }
+    match (base as i64).checked_add(delta) {
+        Some(target) if target < 0 => Err(Einval),
+        Some(target) => Ok(target),
+        None => Err(Eoverflow),
+    }
+}
+
+/// Move the offset of `fd` and write the resulting offset to `newoffset`.
+/// The target offset is computed here rather than by the host so that
+/// out-of-range seeks never reach the file.
+pub fn wasi_fd_seek<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    v_fd: u32,
+    offset: i64,
+    whence: u32,
+    newoffset: P,
+) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_SEEK);
+    unwrap_result!(fd);
+    let whence = match Whence::from_u32(whence) {
+        Some(w) => w,
+        None => return Err(Einval),
+    };
+    let newoffset = ctx.guest_buf(newoffset, 8);
+    unwrap_result!(newoffset);
+    let _r = ctx.check_access(newoffset, 8, Access::Write);
+    unwrap_result!(_r);
+
+    let base = match whence {
+        Whence::Set => Ok(0),
+        Whence::Cur => os_lseek(fd, 0, Whence::Cur),
+        Whence::End => os_file_size(fd),
+    };
+    unwrap_result!(base);
+    let target = seek_target(base, offset);
+    unwrap_result!(target);
+
+    let pos = os_lseek(fd, target, Whence::Set);
+    unwrap_result!(pos);
+    ctx.write_u64(newoffset, pos)
+}
+
+/// Write the current offset of `fd` to `offset`.
+pub fn wasi_fd_tell<P: GuestPtr>(ctx: &mut VmCtx, v_fd: u32, offset: P) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_TELL);
+    unwrap_result!(fd);
+    let offset = ctx.guest_buf(offset, 8);
+    unwrap_result!(offset);
+    let _r = ctx.check_access(offset, 8, Access::Write);
+    unwrap_result!(_r);
+    let pos = os_lseek(fd, 0, Whence::Cur);
+    unwrap_result!(pos);
+    ctx.write_u64(offset, pos)
+}
+
+pub fn wasi_fd_sync(ctx: &VmCtx, v_fd: u32) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_SYNC);
+    unwrap_result!(fd);
+    os_fsync(fd)
+}
+
+pub fn wasi_fd_datasync(ctx: &VmCtx, v_fd: u32) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_DATASYNC);
+    unwrap_result!(fd);
+    os_fdatasync(fd)
+}
+
+pub fn wasi_fd_allocate(ctx: &VmCtx, v_fd: u32, offset: u64, len: u64) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_ALLOCATE);
+    unwrap_result!(fd);
+    match offset.checked_add(len) {
+        Some(end) if end <= i64::MAX as u64 => {}
+        _ => return Err(Efbig),
+    }
+    os_allocate(fd, offset as i64, len as i64)
+}
+
+pub fn wasi_fd_advise(
+    ctx: &VmCtx,
+    v_fd: u32,
+    offset: u64,
+    len: u64,
+    advice: u32,
+) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_ADVISE);
+    unwrap_result!(fd);
+    let advice = match Advice::from_u32(advice) {
+        Some(a) => a,
+        None => return Err(Einval),
+    };
+    if offset > i64::MAX as u64 || len > i64::MAX as u64 {
+        return Err(Einval);
+    }
+    os_advise(fd, offset as i64, len as i64, advice)
+}
+
+/// Write the `iovcnt` buffers described at `iovs` to `fd` and store the number
+/// of bytes written at `nwritten`. A write that would take the sandbox over
+/// its write quota, or that reads a guarded range, is rejected before
+/// anything is written.
+/// `nwritten` is a guest `size`, so it is 8 bytes wide for memory64 guests.
+pub fn wasi_fd_write<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    v_fd: u32,
+    iovs: P,
+    iovcnt: u32,
+    nwritten: P,
+) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_WRITE);
+    unwrap_result!(fd);
+    let nwritten = ctx.guest_buf(nwritten, P::SIZE);
+    unwrap_result!(nwritten);
+    let _r = ctx.check_access(nwritten, P::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let wasm_iovs = parse_iovs(ctx, iovs, iovcnt);
+    unwrap_result!(wasm_iovs);
+    let _r = check_iovs(ctx, &wasm_iovs, Access::Read);
+    unwrap_result!(_r);
+    let _r = ctx.quota.check_write(v_fd, iovs_len(&wasm_iovs));
+    unwrap_result!(_r);
+
+    let native_iovs = ctx.translate_iovs(&wasm_iovs);
+    let n = os_writev(fd, &native_iovs);
+    unwrap_result!(n);
+    ctx.quota.record_write(v_fd, n as u64);
+    ctx.write_ptr::<P>(nwritten, n as u64)
+}
+
+/// Nanoseconds until a clock subscription fires, measured from now.
+fn clock_timeout(id: u32, timeout: u64, abstime: bool) -> RuntimeResult<u64> {
+    let clock = match ClockId::from_u32(id) {
+        Some(c) => c,
+        None => return Err(Einval),
+    };
+    if !abstime {
+        return Ok(timeout);
+    }
+    let now = os_clock_now(clock);
+    unwrap_result!(now);
+    Ok(timeout.saturating_sub(now))
+}
+
+fn timeout_to_ms(timeout: u64) -> i32 {
+    let ms = timeout / 1_000_000 + u64::from(timeout % 1_000_000 != 0);
+    if ms > i32::MAX as u64 {
+        i32::MAX
+    } else {
+        ms as i32
+    }
+}
+
+/// The event reported when `sub` fires, with `error` as its outcome.
+fn sub_event(sub: &Subscription, error: RuntimeError) -> Event {
+    let ty = match sub.kind {
+        SubscriptionKind::Clock { .. } => EventType::Clock,
+        SubscriptionKind::FdRead(_) => EventType::FdRead,
+        SubscriptionKind::FdWrite(_) => EventType::FdWrite,
+    };
+    Event {
+        userdata: sub.userdata,
+        error,
+        ty,
+        nbytes: 0,
+        hangup: false,
+    }
+}
+
+/// Block until one of the `nsubscriptions` subscriptions at `in_` fires, then
+/// write the events that fired to `out` and their count to `nevents`.
+/// Subscriptions on bad fds or unknown clocks fire immediately with an error.
+pub fn wasi_poll_oneoff<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    in_: P,
+    out: P,
+    nsubscriptions: u32,
+    nevents: P,
+) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    if nsubscriptions == 0 {
+        return Err(Einval);
+    }
+    let nevents = ctx.guest_buf(nevents, P::SIZE);
+    unwrap_result!(nevents);
+    let _r = ctx.check_access(nevents, P::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let out_len = (nsubscriptions as usize) * event::SIZE;
+    let events_out = ctx.guest_buf(out, out_len);
+    unwrap_result!(events_out);
+    let _r = ctx.check_access(events_out, out_len, Access::Write);
+    unwrap_result!(_r);
+    let subs = parse_subscriptions(ctx, in_, nsubscriptions);
+    unwrap_result!(subs);
+
+    let mut events = RVec::new();
+    // fds to poll, and the subscription each one came from
+    let mut pollfds = RVec::new();
+    let mut polled = RVec::new();
+    // (subscription, relative timeout) for every valid clock
+    let mut clocks = RVec::new();
+    let mut min_timeout: Option<u64> = None;
+
+    let mut idx = 0;
+    while idx < subs.len() {
+        let sub = subs[idx];
+        match sub.kind {
+            SubscriptionKind::Clock {
+                id,
+                timeout,
+                abstime,
+                ..
+            } => match clock_timeout(id, timeout, abstime) {
+                Ok(t) => {
+                    clocks.push((idx, t));
+                    min_timeout = Some(match min_timeout {
+                        Some(m) if m <= t => m,
+                        _ => t,
+                    });
+                }
+                Err(e) => events.push(sub_event(&sub, e)),
+            },
+            SubscriptionKind::FdRead(v_fd) | SubscriptionKind::FdWrite(v_fd) => {
+                let write = matches!(sub.kind, SubscriptionKind::FdWrite(_));
+                let needed = if write {
+                    Rights::FD_WRITE | Rights::POLL_FD_READWRITE
+                } else {
+                    Rights::FD_READ | Rights::POLL_FD_READWRITE
+                };
+                match ctx.fdmap.fd_to_native(v_fd, needed) {
+                    Ok(fd) => {
+                        pollfds.push(PollFd::new(fd, write));
+                        polled.push(idx);
+                    }
+                    Err(e) => events.push(sub_event(&sub, e)),
+                }
+            }
+        }
+        idx += 1;
+    }
+
+    // don't block if some subscriptions have already fired with an error
+    let timeout_ms = match min_timeout {
+        _ if events.len() > 0 => 0,
+        Some(t) => timeout_to_ms(t),
+        None => -1,
+    };
+    let _r = os_poll(&mut pollfds, timeout_ms);
+    unwrap_result!(_r);
+
+    let mut idx = 0;
+    while idx < pollfds.len() {
+        let pfd = pollfds[idx];
+        if pfd.ready {
+            let sub = subs[polled[idx]];
+            let mut event = sub_event(&sub, Success);
+            if !pfd.write {
+                event.nbytes = os_bytes_readable(pfd.fd).unwrap_or(0);
+            }
+            event.hangup = pfd.hangup;
+            events.push(event);
+        }
+        idx += 1;
+    }
+
+    // nothing else fired, so we timed out: report the earliest clocks
+    if events.len() == 0 {
+        let mut idx = 0;
+        while idx < clocks.len() {
+            let (sub_idx, t) = clocks[idx];
+            if Some(t) == min_timeout {
+                events.push(sub_event(&subs[sub_idx], Success));
+            }
+            idx += 1;
+        }
+    }
+
+    let _r = write_events(ctx, out, &events);
+    unwrap_result!(_r);
+    ctx.write_ptr::<P>(nevents, events.len() as u64)
+}
+
+/// Stop the guest with exit code `rval`. Always returns `Err(Exited)`, which
+/// the embedder must treat as the end of the guest rather than handing it
+/// back; the code is left in `ctx.exit_status`. Once it is set, every wrapper
+/// fails with `Exited` without doing anything.
+pub fn wasi_proc_exit(ctx: &mut VmCtx, rval: u32) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    ctx.exit_status = Some(ExitStatus::Exited(rval));
+    Err(Exited)
+}
+
+/// Handle the guest raising WASI signal `sig` according to
+/// `ctx.raise_policy`.
+pub fn wasi_proc_raise(ctx: &mut VmCtx, sig: u8) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    if sig == 0 || sig > MAX_SIGNAL {
+        return Err(Einval);
+    }
+    match ctx.raise_policy {
+        RaisePolicy::Deny => Err(Enotsup),
+        RaisePolicy::Ignore => Ok(()),
+        RaisePolicy::Terminate => {
+            ctx.exit_status = Some(ExitStatus::Signaled(sig));
+            Err(Exited)
+        }
+    }
+}
+
+pub fn wasi_sched_yield(ctx: &VmCtx) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    os_sched_yield()
+}
+
+const RIFLAGS_RECV_PEEK: u16 = 1;
+const RIFLAGS_RECV_WAITALL: u16 = 2;
+const ROFLAGS_RECV_DATA_TRUNCATED: u16 = 1;
+const FDFLAGS_NONBLOCK: u16 = 4;
+
+/// Rights on a connected or accepted socket.
+fn stream_rights() -> Rights {
+    Rights::FD_READ | Rights::FD_WRITE | Rights::POLL_FD_READWRITE | Rights::SOCK_SHUTDOWN
+}
+
+fn net_allowed(ctx: &VmCtx, endpoint: NetEndpoint) -> bool {
+    let mut idx = 0;
+    while idx < ctx.netlist.len() {
+        if ctx.netlist[idx] == endpoint {
+            return true;
+        }
+        idx += 1;
+    }
+    false
+}
+
+/// Give the sandbox a freshly opened socket, closing it if the fd table is
+/// full.
+fn register_socket(ctx: &mut VmCtx, fd: HostFd, rights: Rights) -> RuntimeResult<u32> {
+    match ctx.create_fd(fd, rights) {
+        Ok(v_fd) => Ok(v_fd),
+        Err(e) => {
+            let _ = os_close(fd);
+            Err(e)
+        }
+    }
+}
+
+/// Open a socket connected to `endpoint`, which must be in the sandbox's
+/// network allowlist, and return its sandbox fd.
+pub fn wasi_sock_connect(ctx: &mut VmCtx, endpoint: NetEndpoint) -> RuntimeResult<u32> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    if endpoint.port > u16::MAX as u32 {
+        return Err(Einval);
+    }
+    if !net_allowed(ctx, endpoint) {
+        return Err(Enotcapable);
+    }
+    let fd = os_socket(endpoint.protocol);
+    unwrap_result!(fd);
+    if let Err(e) = os_connect(fd, endpoint.addr, endpoint.port as u16) {
+        let _ = os_close(fd);
+        return Err(e);
+    }
+    register_socket(ctx, fd, stream_rights())
+}
+
+/// Open a TCP socket listening on `endpoint`, which must be in the sandbox's
+/// network allowlist, and return its sandbox fd. Connections are taken with
+/// `wasi_sock_accept`.
+pub fn wasi_sock_listen(
+    ctx: &mut VmCtx,
+    endpoint: NetEndpoint,
+    backlog: u32,
+) -> RuntimeResult<u32> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    if endpoint.protocol != WasiProto::Tcp {
+        return Err(Enotsup);
+    }
+    if endpoint.port > u16::MAX as u32 {
+        return Err(Einval);
+    }
+    if !net_allowed(ctx, endpoint) {
+        return Err(Enotcapable);
+    }
+    let fd = os_socket(endpoint.protocol);
+    unwrap_result!(fd);
+    if let Err(e) = os_listen(fd, endpoint.addr, endpoint.port as u16, backlog) {
+        let _ = os_close(fd);
+        return Err(e);
+    }
+    register_socket(ctx, fd, Rights::SOCK_ACCEPT | Rights::POLL_FD_READWRITE)
+}
+
+/// Accept a connection on the listening socket `v_fd` and write the new
+/// socket's sandbox fd to `ro_fd`. `flags` are the new socket's `fdflags`,
+/// of which only `nonblock` is supported.
+pub fn wasi_sock_accept<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    v_fd: u32,
+    flags: u16,
+    ro_fd: P,
+) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::SOCK_ACCEPT);
+    unwrap_result!(fd);
+    if flags & !FDFLAGS_NONBLOCK != 0 {
+        return Err(Einval);
+    }
+    let ro_fd = ctx.guest_buf(ro_fd, 4);
+    unwrap_result!(ro_fd);
+    let _r = ctx.check_access(ro_fd, 4, Access::Write);
+    unwrap_result!(_r);
+
+    let conn = os_accept(fd, flags & FDFLAGS_NONBLOCK != 0);
+    unwrap_result!(conn);
+    let conn = register_socket(ctx, conn, stream_rights());
+    unwrap_result!(conn);
+    ctx.write_u32(ro_fd, conn)
+}
+
+/// Receive into the `ri_data_len` buffers described at `ri_data`, then store
+/// the number of bytes received at `ro_datalen` and the `roflags` at
+/// `ro_flags`. `ri_flags` may ask to peek and to wait for every buffer to
+/// fill.
+pub fn wasi_sock_recv<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    v_fd: u32,
+    ri_data: P,
+    ri_data_len: u32,
+    ri_flags: u16,
+    ro_datalen: P,
+    ro_flags: P,
+) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_READ);
+    unwrap_result!(fd);
+    if ri_flags & !(RIFLAGS_RECV_PEEK | RIFLAGS_RECV_WAITALL) != 0 {
+        return Err(Einval);
+    }
+    let ro_datalen = ctx.guest_buf(ro_datalen, P::SIZE);
+    unwrap_result!(ro_datalen);
+    let _r = ctx.check_access(ro_datalen, P::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let ro_flags = ctx.guest_buf(ro_flags, 2);
+    unwrap_result!(ro_flags);
+    let _r = ctx.check_access(ro_flags, 2, Access::Write);
+    unwrap_result!(_r);
+    let wasm_iovs = parse_iovs(ctx, ri_data, ri_data_len);
+    unwrap_result!(wasm_iovs);
+    let _r = check_iovs(ctx, &wasm_iovs, Access::Write);
+    unwrap_result!(_r);
+
+    let mut native_iovs = ctx.translate_iovs(&wasm_iovs);
+    let r = os_recvmsg(
+        fd,
+        &mut native_iovs,
+        ri_flags & RIFLAGS_RECV_PEEK != 0,
+        ri_flags & RIFLAGS_RECV_WAITALL != 0,
+    );
+    unwrap_result!(r);
+    let (n, truncated) = r;
+    mark_iovs_dirty(ctx, &wasm_iovs, n);
+    let _r = ctx.write_ptr::<P>(ro_datalen, n as u64);
+    unwrap_result!(_r);
+    let flags = if truncated {
+        ROFLAGS_RECV_DATA_TRUNCATED
+    } else {
+        0
+    };
+    ctx.write_u16(ro_flags, flags)
+}
+
+/// Send the `si_data_len` buffers described at `si_data` and store the number
+/// of bytes sent at `so_datalen`. Sends count against the write quota like
+/// `fd_write`. No `siflags` are defined, so `si_flags` must be 0.
+pub fn wasi_sock_send<P: GuestPtr>(
+    ctx: &mut VmCtx,
+    v_fd: u32,
+    si_data: P,
+    si_data_len: u32,
+    si_flags: u16,
+    so_datalen: P,
+) -> RuntimeResult<()> {
+    let _r = ctx.ensure_running();
+    unwrap_result!(_r);
+    let fd = ctx.fdmap.fd_to_native(v_fd, Rights::FD_WRITE);
+    unwrap_result!(fd);
+    if si_flags != 0 {
+        return Err(Einval);
+    }
+    let so_datalen = ctx.guest_buf(so_datalen, P::SIZE);
+    unwrap_result!(so_datalen);
+    let _r = ctx.check_access(so_datalen, P::SIZE, Access::Write);
+    unwrap_result!(_r);
+    let wasm_iovs = parse_iovs(ctx, si_data, si_data_len);
+    unwrap_result!(wasm_iovs);
+    let _r = check_iovs(ctx, &wasm_iovs, Access::Read);
+    unwrap_result!(_r);
+    let _r = ctx.quota.check_write(v_fd, iovs_len(&wasm_iovs));
+    unwrap_result!(_r);
+
+    let native_iovs = ctx.translate_iovs(&wasm_iovs);
+    let n = os_sendmsg(fd, &native_iovs);
+    unwrap_result!(n);
+    ctx.quota.record_write(v_fd, n as u64);
+    ctx.write_ptr::<P>(so_datalen, n as u64)
+}
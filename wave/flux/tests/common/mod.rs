@@ -0,0 +1,119 @@
+#![allow(dead_code)]
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::os::unix::fs::symlink;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+
+use wave::rvec::RVec;
+use wave::types::{HostFd, Rights, VmCtx};
+
+pub fn new_ctx() -> VmCtx {
+    VmCtx::new(RVec::new(), 0, RVec::new(), 0)
+}
+
+/// A fresh, unique path under the system temp dir.
+pub fn temp_path(name: &str) -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("wave-{}-{}-{}", std::process::id(), n, name))
+}
+
+/// An anonymous read/write temp file holding `contents`, offset at 0.
+pub fn temp_file(contents: &[u8]) -> File {
+    let path = temp_path("file");
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    file.write_all(contents).unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file
+}
+
+/// Register `file` in the sandbox fd table with `rights`.
+pub fn insert_fd(ctx: &mut VmCtx, file: &impl AsRawFd, rights: Rights) -> u32 {
+    ctx.create_fd(HostFd::from_raw(file.as_raw_fd() as usize), rights)
+        .unwrap()
+}
+
+/// `b` as an `RVec`.
+pub fn bytes(b: &[u8]) -> RVec<u8> {
+    RVec::from_vec(b.to_vec())
+}
+
+/// The host fd of an open directory, for the path resolvers.
+pub fn dirfd(dir: &impl AsRawFd) -> HostFd {
+    HostFd::from_raw(dir.as_raw_fd() as usize)
+}
+
+/// Lay out wasm32 iovecs, each `(base, len)`, starting at `at`.
+pub fn write_iovs(ctx: &mut VmCtx, at: u32, iovs: &[(u32, u32)]) {
+    for (i, (base, len)) in iovs.iter().enumerate() {
+        let start = at as usize + i * 8;
+        ctx.write_u32(start, *base).unwrap();
+        ctx.write_u32(start + 4, *len).unwrap();
+    }
+}
+
+/// Lay out the same iovecs in the memory64 format, starting at `at`.
+pub fn write_iovs64(ctx: &mut VmCtx, at: u64, iovs: &[(u32, u32)]) {
+    for (i, (base, len)) in iovs.iter().enumerate() {
+        let start = at as usize + i * 16;
+        ctx.write_u64(start, *base as u64).unwrap();
+        ctx.write_u64(start + 8, *len as u64).unwrap();
+    }
+}
+
+/// Iovecs for back-to-back buffers of `lens` bytes starting at `data`.
+pub fn packed_iovs(data: u32, lens: &[u32]) -> Vec<(u32, u32)> {
+    let mut base = data;
+    let mut iovs = Vec::new();
+    for len in lens {
+        iovs.push((base, *len));
+        base += len;
+    }
+    iovs
+}
+
+/// A sandbox root full of symlinks that try to get out of it. The
+/// differential tests in `wave/difftest` resolve paths in this same tree.
+pub fn hostile_tree() -> (PathBuf, File) {
+    let root = temp_path("tree");
+    fs::create_dir_all(root.join("dir/sub")).unwrap();
+    fs::write(root.join("dir/file"), b"").unwrap();
+    fs::write(root.join("other"), b"").unwrap();
+    let links = [
+        ("dir/up", ".."),
+        ("dir/upup", "../.."),
+        ("dir/sib", "../other"),
+        ("dir/sub/back", "../../dir"),
+        ("up", ".."),
+        ("abs", "/etc"),
+        ("abs_file", "/etc/passwd"),
+        ("escape", "../"),
+        ("proc", "/proc/self/root"),
+        ("loop1", "loop2"),
+        ("loop2", "loop1"),
+        ("self", "self"),
+        ("dangling", "nowhere"),
+        ("dangling_up", "missing/.."),
+        ("chain", "dir/up/dir/sub/back"),
+        ("c0", "c1"),
+        ("c1", "c2"),
+        ("c2", "c3"),
+        ("c3", "dir/up/c4"),
+        ("c4", "dir/file"),
+        ("c_out", "c1/../../.."),
+    ];
+    for (link, target) in links {
+        symlink(target, root.join(link)).unwrap();
+    }
+    let dir = File::open(&root).unwrap();
+    (root, dir)
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use common::*;
+use wave::path_resolution::{diagnose_path, resolve_path, Rejection, Rule};
+use wave::types::RuntimeError::*;
+
+/// Diagnose a path that must be rejected, checking that the plain resolver
+/// rejects it with the same errno.
+fn rejection(dir: &File, path: &str, should_follow: bool) -> Rejection {
+    let bytes = || bytes(path.as_bytes());
+    let plain = resolve_path(bytes(), should_follow, dirfd(dir)).err();
+    match diagnose_path(bytes(), should_follow, dirfd(dir)) {
+        Ok(_) => panic!("{:?} was accepted", path),
+        Err(r) => {
+            assert_eq!(plain, Some(r.errno), "{:?}", path);
+            r
+        }
+    }
+}
+
+fn symlinks(r: &Rejection) -> Vec<PathBuf> {
+    r.symlinks.as_slice().to_vec()
+}
+
+#[test]
+fn lexical_rejections() {
+    let (root, dir) = hostile_tree();
+
+    let r = rejection(&dir, "", true);
+    assert_eq!(
+        (r.errno, r.rule, r.component),
+        (Enotcapable, Rule::Empty, None)
+    );
+
+    let r = rejection(&dir, "/etc", true);
+    assert_eq!((r.rule, r.component), (Rule::NotRelative, Some(0)));
+
+    let r = rejection(&dir, "dir/../../other", true);
+    assert_eq!((r.errno, r.rule), (Enotcapable, Rule::DepthBelowZero));
+    assert_eq!(r.component, Some(2));
+    assert!(symlinks(&r).is_empty());
+
+    let long = "a/".repeat(2100);
+    let r = rejection(&dir, &long, true);
+    assert_eq!(
+        (r.errno, r.rule, r.component),
+        (Enametoolong, Rule::TooLong, None)
+    );
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn symlink_rejections() {
+    let (root, dir) = hostile_tree();
+
+    let r = rejection(&dir, "dir/upup/x", false);
+    assert_eq!((r.rule, r.component), (Rule::DepthBelowZero, Some(1)));
+    assert_eq!(symlinks(&r), vec![PathBuf::from("../..")]);
+
+    // an absolute symlink in the middle of an otherwise relative path
+    let r = rejection(&dir, "dir/up/abs/passwd", false);
+    assert_eq!((r.errno, r.rule), (Enotcapable, Rule::NotRelative));
+    assert_eq!(r.component, Some(2));
+    assert_eq!(
+        symlinks(&r),
+        vec![PathBuf::from(".."), PathBuf::from("/etc")]
+    );
+
+    let r = rejection(&dir, "c_out", true);
+    assert_eq!((r.rule, r.component), (Rule::DepthBelowZero, Some(0)));
+    let chain: Vec<PathBuf> = ["c1/../../..", "c2", "c3", "dir/up/c4", "..", "dir/file"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    assert_eq!(symlinks(&r), chain);
+
+    let r = rejection(&dir, "dir/../self", true);
+    assert_eq!((r.errno, r.rule), (Eloop, Rule::TooManySymlinks));
+    assert_eq!(r.component, Some(2));
+    assert_eq!(symlinks(&r), vec![PathBuf::from("self"); 10]);
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn accepted_paths_resolve() {
+    let (root, dir) = hostile_tree();
+    for path in ["dir/up/other", "c0", "abs"] {
+        let follow = path != "abs";
+        let bytes = bytes(path.as_bytes());
+        assert!(
+            diagnose_path(bytes, follow, dirfd(&dir)).is_ok(),
+            "{:?}",
+            path
+        );
+    }
+    fs::remove_dir_all(root).unwrap();
+}
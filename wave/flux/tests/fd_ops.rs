@@ -0,0 +1,119 @@
+mod common;
+
+use std::os::unix::net::UnixStream;
+
+use common::*;
+use wave::types::{Rights, RuntimeError::*};
+use wave::wrappers::*;
+
+const OUT: u32 = 0x100;
+
+fn seek_rights() -> Rights {
+    Rights::FD_SEEK | Rights::FD_TELL
+}
+
+#[test]
+fn seek_set_cur_end() {
+    let mut ctx = new_ctx();
+    let file = temp_file(&[7; 100]);
+    let fd = insert_fd(&mut ctx, &file, seek_rights());
+
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 10, 0, OUT), Ok(()));
+    assert_eq!(ctx.read_u64(OUT as usize), 10);
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 5, 1, OUT), Ok(()));
+    assert_eq!(ctx.read_u64(OUT as usize), 15);
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, -1, 2, OUT), Ok(()));
+    assert_eq!(ctx.read_u64(OUT as usize), 99);
+    // seeking past the end is allowed
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 5, 2, OUT), Ok(()));
+    assert_eq!(ctx.read_u64(OUT as usize), 105);
+
+    assert_eq!(wasi_fd_tell(&mut ctx, fd, OUT + 8), Ok(()));
+    assert_eq!(ctx.read_u64(OUT as usize + 8), 105);
+}
+
+#[test]
+fn seek_before_start_is_rejected() {
+    let mut ctx = new_ctx();
+    let file = temp_file(&[0; 16]);
+    let fd = insert_fd(&mut ctx, &file, seek_rights());
+
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 4, 0, OUT), Ok(()));
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, -1, 0, OUT), Err(Einval));
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, -5, 1, OUT), Err(Einval));
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, -17, 2, OUT), Err(Einval));
+
+    // the host offset was never moved
+    assert_eq!(wasi_fd_tell(&mut ctx, fd, OUT), Ok(()));
+    assert_eq!(ctx.read_u64(OUT as usize), 4);
+}
+
+#[test]
+fn seek_offset_overflow() {
+    let mut ctx = new_ctx();
+    let file = temp_file(&[0; 16]);
+    let fd = insert_fd(&mut ctx, &file, seek_rights());
+
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 1, 0, OUT), Ok(()));
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, i64::MAX, 1, OUT), Err(Eoverflow));
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, i64::MAX, 2, OUT), Err(Eoverflow));
+    assert_eq!(wasi_fd_tell(&mut ctx, fd, OUT), Ok(()));
+    assert_eq!(ctx.read_u64(OUT as usize), 1);
+}
+
+#[test]
+fn seek_bad_arguments() {
+    let mut ctx = new_ctx();
+    let file = temp_file(&[0; 16]);
+    let fd = insert_fd(&mut ctx, &file, seek_rights());
+
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 0, 3, OUT), Err(Einval));
+    assert_eq!(wasi_fd_seek(&mut ctx, fd + 1, 0, 0, OUT), Err(Ebadf));
+    assert_eq!(wasi_fd_seek(&mut ctx, 100_000, 0, 0, OUT), Err(Ebadf));
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 0, 0, u32::MAX - 4), Err(Efault));
+    assert_eq!(wasi_fd_tell(&mut ctx, fd, u32::MAX - 4), Err(Efault));
+}
+
+#[test]
+fn seek_on_socket() {
+    let mut ctx = new_ctx();
+    let (a, _b) = UnixStream::pair().unwrap();
+    let fd = insert_fd(&mut ctx, &a, seek_rights());
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 0, 1, OUT), Err(Espipe));
+    assert_eq!(wasi_fd_tell(&mut ctx, fd, OUT), Err(Espipe));
+}
+
+#[test]
+fn rights_come_from_fd_table() {
+    let mut ctx = new_ctx();
+    let file = temp_file(&[0; 16]);
+    let fd = insert_fd(&mut ctx, &file, Rights::FD_READ);
+
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 0, 0, OUT), Err(Enotcapable));
+    assert_eq!(wasi_fd_tell(&mut ctx, fd, OUT), Err(Enotcapable));
+    assert_eq!(wasi_fd_sync(&ctx, fd), Err(Enotcapable));
+    assert_eq!(wasi_fd_datasync(&ctx, fd), Err(Enotcapable));
+    assert_eq!(wasi_fd_allocate(&ctx, fd, 0, 1), Err(Enotcapable));
+    assert_eq!(wasi_fd_advise(&ctx, fd, 0, 0, 0), Err(Enotcapable));
+}
+
+#[test]
+fn sync_allocate_advise() {
+    let mut ctx = new_ctx();
+    let file = temp_file(&[1; 10]);
+    let rights = Rights::FD_SYNC | Rights::FD_DATASYNC | Rights::FD_ALLOCATE | Rights::FD_ADVISE;
+    let fd = insert_fd(&mut ctx, &file, rights);
+
+    assert_eq!(wasi_fd_sync(&ctx, fd), Ok(()));
+    assert_eq!(wasi_fd_datasync(&ctx, fd), Ok(()));
+
+    assert_eq!(wasi_fd_allocate(&ctx, fd, 0, 4096), Ok(()));
+    assert_eq!(file.metadata().unwrap().len(), 4096);
+    assert_eq!(wasi_fd_allocate(&ctx, fd, u64::MAX, 1), Err(Efbig));
+    assert_eq!(wasi_fd_allocate(&ctx, fd, i64::MAX as u64, 1), Err(Efbig));
+
+    assert_eq!(wasi_fd_advise(&ctx, fd, 0, 4096, 1), Ok(()));
+    assert_eq!(wasi_fd_advise(&ctx, fd, 0, 0, 4), Ok(()));
+    assert_eq!(wasi_fd_advise(&ctx, fd, 0, 0, 6), Err(Einval));
+    assert_eq!(wasi_fd_advise(&ctx, fd, u64::MAX, 0, 0), Err(Einval));
+}
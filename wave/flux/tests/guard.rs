@@ -0,0 +1,104 @@
+mod common;
+
+use std::io::{Read, Seek, SeekFrom};
+
+use common::*;
+use wave::guard::{Access, GuardKind};
+use wave::types::{Rights, RuntimeError::*, LINEAR_MEM_SIZE};
+use wave::wrappers::wasi_fd_write;
+
+const DATA: usize = 0x1000;
+const GUARD: usize = 0x2000;
+const IOVS: u32 = 0x3000;
+const NWRITTEN: u32 = 0x3100;
+
+#[test]
+fn read_only_refuses_writes() {
+    let mut ctx = new_ctx();
+    ctx.write_u32(GUARD, 0xdeadbeef).unwrap();
+    ctx.add_guard(GUARD, 16, GuardKind::ReadOnly).unwrap();
+
+    assert_eq!(ctx.write_u8(GUARD, 0), Err(Efault));
+    // a write that only straddles the start of the region is refused too
+    assert_eq!(ctx.write_u64(GUARD - 4, 0), Err(Efault));
+    assert_eq!(
+        ctx.copy_buf_to_sandbox(GUARD as u32 + 15, &bytes(b"ab"), 2),
+        Err(Efault)
+    );
+    assert_eq!(ctx.read_u32(GUARD), 0xdeadbeef);
+
+    // reads and neighbouring writes are fine
+    assert_eq!(ctx.read_guest_bytes(GUARD as u32, 4).unwrap().len(), 4);
+    assert_eq!(ctx.write_u32(GUARD - 4, 1), Ok(()));
+    assert_eq!(ctx.write_u32(GUARD + 16, 1), Ok(()));
+    assert_eq!(ctx.check_access(GUARD, 16, Access::Read), Ok(()));
+}
+
+#[test]
+fn poisoned_refuses_reads_and_writes() {
+    let mut ctx = new_ctx();
+    ctx.copy_buf_to_sandbox(GUARD as u32, &bytes(b"secret\0"), 7)
+        .unwrap();
+    ctx.add_guard(GUARD, 7, GuardKind::Poisoned).unwrap();
+
+    assert_eq!(ctx.write_u16(GUARD + 6, 0), Err(Efault));
+    assert!(matches!(ctx.read_guest_bytes(GUARD as u32, 1), Err(Efault)));
+    assert!(matches!(ctx.read_guest_cstr(GUARD as u32, 64), Err(Efault)));
+    assert!(matches!(ctx.read_ptr_pair::<u32>(GUARD - 4), Err(Efault)));
+
+    // a C string running into the poisoned range is refused as well
+    ctx.copy_buf_to_sandbox(GUARD as u32 - 3, &bytes(b"abc"), 3)
+        .unwrap();
+    assert!(matches!(
+        ctx.read_guest_cstr(GUARD as u32 - 3, 64),
+        Err(Efault)
+    ));
+}
+
+#[test]
+fn fd_write_respects_guards() {
+    let mut ctx = new_ctx();
+    let mut file = temp_file(b"");
+    let fd = insert_fd(&mut ctx, &file, Rights::FD_WRITE);
+    ctx.copy_buf_to_sandbox(DATA as u32, &bytes(b"hello"), 5)
+        .unwrap();
+    write_iovs(&mut ctx, IOVS, &[(DATA as u32, 5)]);
+
+    // the source buffer is poisoned: nothing reaches the file
+    ctx.add_guard(DATA + 4, 1, GuardKind::Poisoned).unwrap();
+    assert_eq!(wasi_fd_write(&mut ctx, fd, IOVS, 1, NWRITTEN), Err(Efault));
+
+    // `nwritten` is read-only: refused before the write happens
+    ctx.clear_guards();
+    ctx.add_guard(NWRITTEN as usize, 4, GuardKind::ReadOnly)
+        .unwrap();
+    assert_eq!(wasi_fd_write(&mut ctx, fd, IOVS, 1, NWRITTEN), Err(Efault));
+
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "");
+
+    // read-only source buffers may still be written out
+    ctx.clear_guards();
+    ctx.add_guard(DATA, 5, GuardKind::ReadOnly).unwrap();
+    assert_eq!(wasi_fd_write(&mut ctx, fd, IOVS, 1, NWRITTEN), Ok(()));
+    assert_eq!(ctx.read_u32(NWRITTEN as usize), 5);
+}
+
+#[test]
+fn bad_guards_are_rejected() {
+    let mut ctx = new_ctx();
+    let end = LINEAR_MEM_SIZE;
+    assert_eq!(ctx.add_guard(GUARD, 0, GuardKind::Poisoned), Err(Einval));
+    assert_eq!(ctx.add_guard(end - 4, 8, GuardKind::Poisoned), Err(Einval));
+    assert_eq!(
+        ctx.add_guard(usize::MAX, 2, GuardKind::ReadOnly),
+        Err(Einval)
+    );
+    // overlapping regions are allowed and the strictest wins
+    ctx.add_guard(GUARD, 32, GuardKind::ReadOnly).unwrap();
+    ctx.add_guard(GUARD + 8, 8, GuardKind::Poisoned).unwrap();
+    assert_eq!(ctx.check_access(GUARD, 8, Access::Read), Ok(()));
+    assert_eq!(ctx.check_access(GUARD + 12, 1, Access::Read), Err(Efault));
+}
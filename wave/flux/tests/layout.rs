@@ -0,0 +1,316 @@
+mod common;
+
+use std::fmt::Debug;
+
+use common::*;
+use wave::guard::GuardKind;
+use wave::layout::*;
+use wave::rvec::RVec;
+use wave::types::*;
+
+const AT: u32 = 0x1000;
+
+/// Encoding `value` must give exactly `golden`, padding included, whatever
+/// was in memory before; decoding `golden` must give `value` back.
+fn check<T: PartialEq + Debug>(
+    golden: &[u8],
+    value: T,
+    encode: fn(&mut VmCtx, usize, &T) -> RuntimeResult<()>,
+    decode: fn(&VmCtx, usize) -> RuntimeResult<T>,
+) {
+    let mut ctx = new_ctx();
+    let n = golden.len() as u32;
+    let junk = RVec::from_vec(vec![0xaa; golden.len()]);
+    ctx.copy_buf_to_sandbox(AT, &junk, n).unwrap();
+    encode(&mut ctx, AT as usize, &value).unwrap();
+    assert_eq!(ctx.read_guest_bytes(AT, n).unwrap().as_slice(), golden);
+
+    let mut ctx = new_ctx();
+    let golden_buf = RVec::from_vec(golden.to_vec());
+    ctx.copy_buf_to_sandbox(AT, &golden_buf, n).unwrap();
+    assert_eq!(decode(&ctx, AT as usize), Ok(value));
+}
+
+#[test]
+fn sizes_and_offsets() {
+    assert_eq!((iovec::size::<u32>(), iovec::align::<u32>()), (8, 4));
+    assert_eq!((iovec::BUF, iovec::buf_len::<u32>()), (0, 4));
+    assert_eq!((iovec::size::<u64>(), iovec::align::<u64>()), (16, 8));
+    assert_eq!((ciovec::BUF, ciovec::buf_len::<u64>()), (0, 8));
+
+    assert_eq!((prestat::size::<u32>(), prestat::align::<u32>()), (8, 4));
+    assert_eq!((prestat::TAG, prestat::name_len::<u32>()), (0, 4));
+    assert_eq!((prestat::size::<u64>(), prestat::align::<u64>()), (16, 8));
+    assert_eq!(prestat::name_len::<u64>(), 8);
+
+    assert_eq!((fdstat::SIZE, fdstat::ALIGN), (24, 8));
+    assert_eq!(
+        (
+            fdstat::FILETYPE,
+            fdstat::FLAGS,
+            fdstat::RIGHTS_BASE,
+            fdstat::RIGHTS_INHERITING
+        ),
+        (0, 2, 8, 16)
+    );
+
+    assert_eq!((filestat::SIZE, filestat::ALIGN), (64, 8));
+    assert_eq!(
+        [
+            filestat::DEV,
+            filestat::INO,
+            filestat::FILETYPE,
+            filestat::NLINK,
+            filestat::FILE_SIZE,
+            filestat::ATIM,
+            filestat::MTIM,
+            filestat::CTIM
+        ],
+        [0, 8, 16, 24, 32, 40, 48, 56]
+    );
+
+    assert_eq!((dirent::SIZE, dirent::ALIGN), (24, 8));
+    assert_eq!(
+        [dirent::NEXT, dirent::INO, dirent::NAMLEN, dirent::TYPE],
+        [0, 8, 16, 20]
+    );
+
+    assert_eq!((event::SIZE, event::ALIGN), (32, 8));
+    assert_eq!(
+        [
+            event::USERDATA,
+            event::ERROR,
+            event::TYPE,
+            event::NBYTES,
+            event::FLAGS
+        ],
+        [0, 8, 10, 16, 24]
+    );
+
+    assert_eq!((subscription::SIZE, subscription::ALIGN), (48, 8));
+    assert_eq!(
+        [
+            subscription::USERDATA,
+            subscription::TAG,
+            subscription::CLOCK_ID,
+            subscription::CLOCK_TIMEOUT,
+            subscription::CLOCK_PRECISION,
+            subscription::CLOCK_FLAGS,
+            subscription::FD
+        ],
+        [0, 8, 16, 24, 32, 40, 16]
+    );
+}
+
+#[test]
+fn golden_iovec() {
+    let iov = WasmIoVec {
+        iov_base: 0x1122_3344,
+        iov_len: 0x5566_7788,
+    };
+    check(
+        &[0x44, 0x33, 0x22, 0x11, 0x88, 0x77, 0x66, 0x55],
+        iov,
+        encode_iovec::<u32>,
+        decode_iovec::<u32>,
+    );
+    check(
+        &[
+            0x44, 0x33, 0x22, 0x11, 0, 0, 0, 0, //
+            0x88, 0x77, 0x66, 0x55, 0, 0, 0, 0,
+        ],
+        iov,
+        encode_iovec::<u64>,
+        decode_iovec::<u64>,
+    );
+}
+
+#[test]
+fn golden_prestat() {
+    check(
+        &[0, 0, 0, 0, 5, 0, 0, 0],
+        Prestat { name_len: 5 },
+        encode_prestat::<u32>,
+        decode_prestat::<u32>,
+    );
+    // `pr_name_len` is a `size`
+    check(
+        &[0, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0],
+        Prestat { name_len: 5 },
+        encode_prestat::<u64>,
+        decode_prestat::<u64>,
+    );
+}
+
+#[test]
+fn golden_fdstat() {
+    check(
+        &[
+            4, 0, 5, 0, 0, 0, 0, 0, //
+            0x04, 0x20, 0, 0x20, 0, 0, 0, 0, //
+            0x42, 0, 0, 0, 0, 0, 0, 0,
+        ],
+        Fdstat {
+            filetype: Filetype::RegularFile,
+            flags: 5,
+            rights_base: Rights::FD_SEEK | Rights::PATH_OPEN | Rights::SOCK_ACCEPT,
+            rights_inheriting: Rights::FD_READ | Rights::FD_WRITE,
+        },
+        encode_fdstat,
+        decode_fdstat,
+    );
+}
+
+#[test]
+fn golden_filestat() {
+    check(
+        &[
+            1, 0, 0, 0, 0, 0, 0, 0, //
+            2, 0, 0, 0, 0, 0, 0, 0, //
+            3, 0, 0, 0, 0, 0, 0, 0, //
+            4, 0, 0, 0, 0, 0, 0, 0, //
+            0, 0x10, 0, 0, 0, 0, 0, 0, //
+            6, 0, 0, 0, 0, 0, 0, 0, //
+            7, 0, 0, 0, 0, 0, 0, 0, //
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        ],
+        Filestat {
+            dev: 1,
+            ino: 2,
+            filetype: Filetype::Directory,
+            nlink: 4,
+            size: 0x1000,
+            atim: 6,
+            mtim: 7,
+            ctim: u64::MAX,
+        },
+        encode_filestat,
+        decode_filestat,
+    );
+}
+
+#[test]
+fn golden_dirent() {
+    check(
+        &[
+            0x0a, 0, 0, 0, 0, 0, 0, 0, //
+            0x0b, 0, 0, 0, 0, 0, 0, 0, //
+            3, 0, 0, 0, 7, 0, 0, 0,
+        ],
+        Dirent {
+            next: 0x0a,
+            ino: 0x0b,
+            namlen: 3,
+            filetype: Filetype::SymbolicLink,
+        },
+        encode_dirent,
+        decode_dirent,
+    );
+}
+
+#[test]
+fn golden_event() {
+    check(
+        &[
+            0xad, 0xde, 0, 0, 0, 0, 0, 0, //
+            8, 0, 1, 0, 0, 0, 0, 0, //
+            0, 1, 0, 0, 0, 0, 0, 0, //
+            1, 0, 0, 0, 0, 0, 0, 0,
+        ],
+        Event {
+            userdata: 0xdead,
+            error: RuntimeError::Ebadf,
+            ty: EventType::FdRead,
+            nbytes: 0x100,
+            hangup: true,
+        },
+        encode_event,
+        decode_event,
+    );
+}
+
+#[test]
+fn golden_subscription() {
+    check(
+        &[
+            7, 0, 0, 0, 0, 0, 0, 0, //
+            0, 0, 0, 0, 0, 0, 0, 0, //
+            1, 0, 0, 0, 0, 0, 0, 0, //
+            0xe8, 0x03, 0, 0, 0, 0, 0, 0, //
+            0x10, 0, 0, 0, 0, 0, 0, 0, //
+            1, 0, 0, 0, 0, 0, 0, 0,
+        ],
+        Subscription {
+            userdata: 7,
+            kind: SubscriptionKind::Clock {
+                id: 1,
+                timeout: 1000,
+                precision: 0x10,
+                abstime: true,
+            },
+        },
+        encode_subscription,
+        decode_subscription,
+    );
+    let mut fd_write = [0; 48];
+    fd_write[0] = 8;
+    fd_write[8] = 2;
+    fd_write[16] = 9;
+    check(
+        &fd_write,
+        Subscription {
+            userdata: 8,
+            kind: SubscriptionKind::FdWrite(9),
+        },
+        encode_subscription,
+        decode_subscription,
+    );
+}
+
+#[test]
+fn bad_tags_and_ranges() {
+    let mut ctx = new_ctx();
+    ctx.write_u8(AT as usize + subscription::TAG, 3).unwrap();
+    assert_eq!(
+        decode_subscription(&ctx, AT as usize),
+        Err(RuntimeError::Einval)
+    );
+    ctx.write_u8(AT as usize + fdstat::FILETYPE, 8).unwrap();
+    assert_eq!(decode_fdstat(&ctx, AT as usize), Err(RuntimeError::Einval));
+    ctx.write_u8(AT as usize + prestat::TAG, 1).unwrap();
+    assert_eq!(
+        decode_prestat::<u32>(&ctx, AT as usize),
+        Err(RuntimeError::Einval)
+    );
+
+    // the whole struct must fit, and encoders write nothing if it does not
+    let end = LINEAR_MEM_SIZE - 16;
+    let stat = Prestat { name_len: 1 };
+    assert_eq!(decode_filestat(&ctx, end), Err(RuntimeError::Efault));
+    assert_eq!(
+        encode_dirent(
+            &mut ctx,
+            end,
+            &Dirent {
+                next: 1,
+                ino: 1,
+                namlen: 1,
+                filetype: Filetype::Unknown,
+            }
+        ),
+        Err(RuntimeError::Efault)
+    );
+    assert_eq!(ctx.read_u64(end), 0);
+    assert_eq!(
+        encode_prestat::<u32>(&mut ctx, usize::MAX - 4, &stat),
+        Err(RuntimeError::Efault)
+    );
+
+    ctx.add_guard(AT as usize + 4, 1, GuardKind::ReadOnly)
+        .unwrap();
+    assert_eq!(
+        encode_prestat::<u32>(&mut ctx, AT as usize, &stat),
+        Err(RuntimeError::Efault)
+    );
+    assert_eq!(ctx.read_u8(AT as usize + prestat::TAG), 1);
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use std::fs::{self, File};
+use std::os::unix::fs::symlink;
+use std::os::unix::io::AsRawFd;
+
+use common::*;
+use wave::link_cache::LinkCache;
+use wave::path_resolution::{resolve_path, resolve_path_cached};
+use wave::types::{HostFd, RuntimeError, RuntimeError::*};
+
+// where the "c0" symlink chain in `hostile_tree` leads
+const C0: &[u8] = b"dir/../dir/file";
+
+fn resolve_at(dirfd: HostFd, path: &str, cache: &mut LinkCache) -> Result<Vec<u8>, RuntimeError> {
+    let path = bytes(path.as_bytes());
+    resolve_path_cached(path, true, dirfd, cache).map(|p| p.as_bytes().to_vec())
+}
+
+fn resolve(dir: &File, path: &str, cache: &mut LinkCache) -> Result<Vec<u8>, RuntimeError> {
+    resolve_at(dirfd(dir), path, cache)
+}
+
+#[test]
+fn repeated_prefixes_hit() {
+    let (_root, dir) = hostile_tree();
+    let mut cache = LinkCache::default();
+
+    // "c0" is a chain of symlinks ending at dir/file
+    let first = resolve(&dir, "c0", &mut cache);
+    assert_eq!(first, Ok(C0.to_vec()));
+    let misses = cache.misses();
+    assert_eq!(cache.hits(), 0);
+
+    assert_eq!(resolve(&dir, "c0", &mut cache), first);
+    assert_eq!(cache.misses(), misses);
+    assert_eq!(cache.hits(), misses);
+
+    // prefixes seen while expanding the chain are cached too
+    assert_eq!(resolve(&dir, "dir", &mut cache), Ok(b"dir".to_vec()));
+    assert_eq!(cache.misses(), misses);
+}
+
+#[test]
+fn cached_answers_match_uncached() {
+    let (_root, dir) = hostile_tree();
+    let mut cache = LinkCache::default();
+    let paths = [
+        "dir/file",
+        "dir/up",
+        "dir/sib",
+        "abs",
+        "escape",
+        "loop1",
+        "c0",
+        "c_out",
+        "dangling",
+        "up",
+        "chain",
+        "dangling_up",
+    ];
+    for _ in 0..2 {
+        for path in paths {
+            let plain = bytes(path.as_bytes());
+            let plain = resolve_path(plain, true, dirfd(&dir)).map(|p| p.as_bytes().to_vec());
+            assert_eq!(resolve(&dir, path, &mut cache), plain, "{:?}", path);
+        }
+    }
+    assert!(cache.hits() > 0);
+}
+
+#[test]
+fn missing_prefixes_are_not_cached() {
+    let root = temp_path("cache");
+    fs::create_dir_all(root.join("a")).unwrap();
+    let dir = File::open(&root).unwrap();
+    let mut cache = LinkCache::default();
+
+    // a missing path resolves, but must not be remembered as "not a symlink"
+    assert_eq!(resolve(&dir, "a/b", &mut cache), Ok(b"a/b".to_vec()));
+    symlink("../..", root.join("a/b")).unwrap();
+    assert_eq!(resolve(&dir, "a/b", &mut cache), Err(Enotcapable));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn keyed_by_dirfd() {
+    let (_root, hostile) = hostile_tree();
+    let tame = temp_path("tame");
+    fs::create_dir_all(tame.join("escape")).unwrap();
+    let tame = File::open(&tame).unwrap();
+    let mut cache = LinkCache::default();
+
+    // "escape" is a directory under one fd and a symlink to ".." under the other
+    assert_eq!(resolve(&tame, "escape", &mut cache), Ok(b"escape".to_vec()));
+    assert_eq!(resolve(&hostile, "escape", &mut cache), Err(Enotcapable));
+}
+
+#[test]
+fn reused_dirfd_does_not_inherit_entries() {
+    let (_root, hostile) = hostile_tree();
+    let tame = temp_path("tame");
+    fs::create_dir_all(tame.join("escape")).unwrap();
+    let tame = File::open(&tame).unwrap();
+    let mut cache = LinkCache::default();
+
+    let fd = unsafe { libc::dup(tame.as_raw_fd()) };
+    assert!(fd >= 0);
+    let reused = HostFd::from_raw(fd as usize);
+    assert_eq!(
+        resolve_at(reused, "escape", &mut cache),
+        Ok(b"escape".to_vec())
+    );
+    // the same fd number, now naming the hostile tree
+    assert_eq!(unsafe { libc::dup2(hostile.as_raw_fd(), fd) }, fd);
+    assert_eq!(resolve_at(reused, "escape", &mut cache), Err(Enotcapable));
+    unsafe { libc::close(fd) };
+}
+
+#[test]
+fn size_is_bounded() {
+    let (_root, dir) = hostile_tree();
+
+    let mut cache = LinkCache::new(2);
+    for path in ["dir/file", "other", "c0", "dir/up/dir/file"] {
+        resolve(&dir, path, &mut cache).unwrap();
+        assert!(cache.len() <= 2);
+    }
+    assert_eq!(resolve(&dir, "c0", &mut cache), Ok(C0.to_vec()));
+
+    let mut off = LinkCache::new(0);
+    assert_eq!(resolve(&dir, "c0", &mut off), Ok(C0.to_vec()));
+    assert!(off.is_empty());
+    assert_eq!(off.hits(), 0);
+}
+
+#[test]
+fn translate_path_uses_ctx_cache() {
+    let (_root, dir) = hostile_tree();
+    let mut ctx = new_ctx();
+    let path = b"c0";
+    ctx.copy_buf_to_sandbox(0x100, &bytes(path), 2).unwrap();
+
+    for _ in 0..2 {
+        let p = ctx.translate_path(0x100, 2, true, dirfd(&dir));
+        assert_eq!(p.map(|p| p.as_bytes().to_vec()), Ok(C0.to_vec()));
+    }
+    assert!(ctx.link_cache.hits() > 0);
+
+    // restoring a snapshot starts over with a cold cache
+    let snap = ctx.snapshot();
+    assert!(!ctx.link_cache.is_empty());
+    ctx.restore(&snap);
+    assert!(ctx.link_cache.is_empty());
+}
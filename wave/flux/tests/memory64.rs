@@ -0,0 +1,109 @@
+mod common;
+
+use std::io::{Read, Seek, SeekFrom};
+
+use common::*;
+use wave::iov::parse_iovs;
+use wave::types::{Rights, RuntimeError::*, VmCtx};
+use wave::wrappers::{wasi_fd_seek, wasi_fd_write};
+
+const IOVS32: u32 = 0x100;
+const IOVS64: u64 = 0x200;
+const DATA: usize = 0x1000;
+const NWRITTEN: u64 = 0x2000;
+
+/// Lay out the same iovecs in both the wasm32 and memory64 formats.
+fn write_both_iovs(ctx: &mut VmCtx, iovs: &[(u32, u32)]) {
+    write_iovs(ctx, IOVS32, iovs);
+    write_iovs64(ctx, IOVS64, iovs);
+}
+
+#[test]
+fn layouts_translate_identically() {
+    let mut ctx = new_ctx();
+    write_both_iovs(&mut ctx, &[(0x1000, 3), (0x1100, 0), (0x1200, 17)]);
+    let narrow = parse_iovs(&ctx, IOVS32, 3).unwrap();
+    let wide = parse_iovs(&ctx, IOVS64, 3).unwrap();
+    assert_eq!(narrow.as_slice(), wide.as_slice());
+    assert_eq!(
+        ctx.translate_iovs(&narrow).to_vec(),
+        ctx.translate_iovs(&wide).to_vec()
+    );
+}
+
+#[test]
+fn fd_write_with_wide_iovecs() {
+    let mut ctx = new_ctx();
+    let mut file = temp_file(b"");
+    let fd = insert_fd(&mut ctx, &file, Rights::FD_WRITE);
+    for (i, b) in b"hello world".iter().enumerate() {
+        ctx.write_u8(DATA + i, *b).unwrap();
+    }
+    write_both_iovs(&mut ctx, &[(DATA as u32, 6), (DATA as u32 + 6, 5)]);
+    // stale high bytes must be overwritten by the 8 byte `size`
+    ctx.write_u64(NWRITTEN as usize, u64::MAX).unwrap();
+    assert_eq!(wasi_fd_write(&mut ctx, fd, IOVS64, 2, NWRITTEN), Ok(()));
+    assert_eq!(ctx.read_u64(NWRITTEN as usize), 11);
+
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "hello world");
+}
+
+#[test]
+fn wide_pointers_out_of_bounds() {
+    let mut ctx = new_ctx();
+    let file = temp_file(b"");
+    let fd = insert_fd(&mut ctx, &file, Rights::FD_WRITE | Rights::FD_SEEK);
+
+    // iovec array above the end of linear memory, or wrapping around, is
+    // `Eoverflow` just as for wasm32 guests
+    for iovs in [1 << 32, u64::MAX - 8, u64::MAX] {
+        assert!(matches!(parse_iovs(&ctx, iovs, 1), Err(Eoverflow)));
+    }
+    // the array starts in bounds but its stride runs off the end
+    assert!(matches!(parse_iovs(&ctx, u64::MAX - 40, 3), Err(Eoverflow)));
+
+    // iovecs whose buffers are out of bounds or wrap around
+    for (base, len) in [(1 << 32, 1), (u64::MAX, 2), (0x1000, u64::MAX - 0x800)] {
+        ctx.write_u64(IOVS64 as usize, base).unwrap();
+        ctx.write_u64(IOVS64 as usize + 8, len).unwrap();
+        assert!(matches!(parse_iovs(&ctx, IOVS64, 1), Err(Efault)));
+        assert_eq!(
+            wasi_fd_write(&mut ctx, fd, IOVS64, 1, NWRITTEN),
+            Err(Efault)
+        );
+    }
+
+    assert_eq!(
+        wasi_fd_write(&mut ctx, fd, IOVS64, 0, u64::MAX - 4),
+        Err(Efault)
+    );
+    assert_eq!(wasi_fd_seek(&mut ctx, fd, 0, 0, 1u64 << 40), Err(Efault));
+    assert_eq!(file.metadata().unwrap().len(), 0);
+}
+
+#[test]
+fn pointer_reads_past_the_end_overflow() {
+    let ctx = new_ctx();
+    let end = ctx.memlen;
+    // the same error the wasm32-only read_u32_pair gives
+    assert!(matches!(ctx.read_u32_pair(end - 4), Err(Eoverflow)));
+    assert!(matches!(ctx.read_ptr::<u32>(end - 2), Err(Eoverflow)));
+    assert!(matches!(ctx.read_ptr::<u64>(end - 4), Err(Eoverflow)));
+    assert!(matches!(ctx.read_ptr_pair::<u32>(end - 4), Err(Eoverflow)));
+    assert!(matches!(ctx.read_ptr_pair::<u64>(end - 8), Err(Eoverflow)));
+}
+
+#[test]
+fn wide_values_overflow_wasm32_pointers() {
+    let mut ctx = new_ctx();
+    let big = u32::MAX as u64 + 1;
+    assert!(matches!(ctx.write_ptr::<u32>(0x100, big), Err(Eoverflow)));
+    assert_eq!(ctx.read_u32(0x100), 0);
+    ctx.write_ptr::<u32>(0x100, u32::MAX as u64).unwrap();
+    assert_eq!(ctx.read_ptr::<u32>(0x100).unwrap(), u32::MAX as u64);
+    ctx.write_ptr::<u64>(0x100, big).unwrap();
+    assert_eq!(ctx.read_ptr::<u64>(0x100).unwrap(), big);
+}
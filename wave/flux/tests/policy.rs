@@ -0,0 +1,175 @@
+mod common;
+
+use std::fs;
+
+use common::*;
+use wave::policy::{load_policy, Policy, PolicyError};
+use wave::tcb::path::{NetEndpoint, WasiProto};
+use wave::types::{RaisePolicy, Rights};
+
+fn parse_err(text: &str) -> PolicyError {
+    Policy::parse(text).unwrap_err()
+}
+
+#[test]
+fn full_policy() {
+    let data = temp_path("data");
+    let tmp = temp_path("tmp");
+    fs::create_dir(&data).unwrap();
+    fs::create_dir(&tmp).unwrap();
+    let text = format!(
+        "# a sandbox for tests\n\
+         preopen {} /data fd_read path_open\n\
+         \n\
+         preopen {} /tmp\n\
+         arg prog.wasm\n\
+         arg --flag value with spaces\n\
+         env HOME=/data\n\
+         env EMPTY=\n\
+         quota max_open_fds 8\n\
+         quota max_bytes_written 4096\n\
+         net tcp 127.0.0.1:8080\n\
+         net udp 10.0.0.1:53\n\
+         raise ignore\n",
+        data.display(),
+        tmp.display()
+    );
+    let path = temp_path("policy");
+    fs::write(&path, text).unwrap();
+    let ctx = load_policy(&path).unwrap();
+
+    assert_eq!(
+        ctx.arg_buffer.clone().to_vec(),
+        b"prog.wasm\0--flag value with spaces\0"
+    );
+    assert_eq!(ctx.argc, 2);
+    assert_eq!(ctx.env_buffer.clone().to_vec(), b"HOME=/data\0EMPTY=\0");
+    assert_eq!(ctx.envc, 2);
+    assert_eq!(ctx.quota.limits.max_open_fds, 8);
+    assert_eq!(ctx.quota.limits.max_bytes_written, 4096);
+    assert_eq!(ctx.quota.limits.max_iovecs, 1024);
+    assert_eq!(ctx.raise_policy, RaisePolicy::Ignore);
+
+    let preopens = ctx.preopens.as_slice();
+    assert_eq!(preopens.len(), 2);
+    assert_eq!(preopens[0].name.clone().to_vec(), b"/data");
+    assert_eq!(preopens[1].name.clone().to_vec(), b"/tmp");
+    let data_fd = ctx.fdmap.lookup(preopens[0].fd).unwrap();
+    assert_eq!(data_fd.rights, Rights::FD_READ | Rights::PATH_OPEN);
+    let tmp_fd = ctx.fdmap.lookup(preopens[1].fd).unwrap();
+    assert_eq!(tmp_fd.rights, Rights::all());
+
+    assert_eq!(
+        ctx.netlist.as_slice(),
+        &[
+            NetEndpoint {
+                protocol: WasiProto::Tcp,
+                addr: 0x7f00_0001,
+                port: 8080,
+            },
+            NetEndpoint {
+                protocol: WasiProto::Udp,
+                addr: 0x0a00_0001,
+                port: 53,
+            },
+        ]
+    );
+
+    fs::remove_file(path).unwrap();
+    fs::remove_dir(data).unwrap();
+    fs::remove_dir(tmp).unwrap();
+}
+
+#[test]
+fn syntax_errors_have_line_numbers() {
+    let cases = [
+        ("bogus x", 1, "unknown directive `bogus`"),
+        ("arg a\n\npreopen /only-host", 3, "expected `preopen"),
+        ("preopen /a /a fd_read fd_fly", 1, "unknown right `fd_fly`"),
+        ("preopen /a /x\npreopen /b /x", 2, "duplicate preopen `/x`"),
+        ("# comment\nenv NOVALUE", 2, "expected `env KEY=VALUE`"),
+        ("env =x", 1, "expected `env KEY=VALUE`"),
+        ("quota max_open_fds", 1, "expected `quota LIMIT VALUE`"),
+        ("quota max_open_fds lots", 1, "invalid value `lots`"),
+        ("quota max_iovecs -1", 1, "invalid value `-1`"),
+        ("quota max_cpu 1", 1, "unknown quota `max_cpu`"),
+        ("net sctp 1.2.3.4:5", 1, "unknown protocol `sctp`"),
+        ("net tcp 1.2.3:5", 1, "invalid endpoint `1.2.3:5`"),
+        ("net tcp 1.2.3.4:70000", 1, "invalid endpoint"),
+        ("net tcp 1.2.3.4", 1, "invalid endpoint"),
+        ("raise kill", 1, "expected `raise deny|ignore|terminate`"),
+        ("arg a\0b", 1, "NUL bytes"),
+    ];
+    for (text, line, msg) in cases {
+        let e = parse_err(text);
+        assert_eq!(e.line, Some(line), "{:?}", text);
+        assert!(e.msg.starts_with(msg), "{:?}: {}", text, e.msg);
+        assert!(e.to_string().starts_with(&format!("line {}: ", line)));
+    }
+}
+
+#[test]
+fn load_errors() {
+    let missing = temp_path("missing");
+    let text = format!("arg x\npreopen {} /m\n", missing.display());
+    let e = Policy::parse(&text).unwrap().build().err().unwrap();
+    assert_eq!(e.line, Some(2));
+    assert!(e.msg.starts_with("cannot open"), "{}", e.msg);
+
+    // the fd quota applies to preopens too
+    let dir = std::env::temp_dir();
+    let text = format!(
+        "quota max_open_fds 1\npreopen {0} /a\npreopen {0} /b\n",
+        dir.display()
+    );
+    let e = Policy::parse(&text).unwrap().build().err().unwrap();
+    assert_eq!(e.line, Some(3));
+    assert!(e.msg.contains("Emfile"), "{}", e.msg);
+
+    let e = load_policy(&missing).err().unwrap();
+    assert_eq!(e.line, None);
+    assert!(e.to_string().starts_with("cannot read"));
+}
+
+/// Host fds of this process that refer to `path`.
+fn open_fds_to(path: &std::path::Path) -> usize {
+    fs::read_dir("/proc/self/fd")
+        .unwrap()
+        .filter_map(|ent| fs::read_link(ent.unwrap().path()).ok())
+        .filter(|target| target == path)
+        .count()
+}
+
+#[test]
+fn failed_build_closes_earlier_preopens() {
+    let dir = temp_path("preopen");
+    fs::create_dir(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    let missing = temp_path("missing");
+    let text = format!(
+        "preopen {} /a\npreopen {} /b\npreopen {} /c\n",
+        dir.display(),
+        dir.display(),
+        missing.display()
+    );
+    let e = Policy::parse(&text).unwrap().build().err().unwrap();
+    assert_eq!(e.line, Some(3));
+    assert_eq!(open_fds_to(&dir), 0);
+
+    // and when the fd quota turns the second preopen away
+    let text = format!(
+        "quota max_open_fds 1\npreopen {0} /a\npreopen {0} /b\n",
+        dir.display()
+    );
+    let e = Policy::parse(&text).unwrap().build().err().unwrap();
+    assert_eq!(e.line, Some(3));
+    assert_eq!(open_fds_to(&dir), 0);
+    fs::remove_dir(dir).unwrap();
+}
+
+#[test]
+fn too_many_args() {
+    let text = "arg x\n".repeat(1024);
+    assert_eq!(parse_err(&text).line, Some(1024));
+    assert!(Policy::parse(&"arg x\n".repeat(1023)).is_ok());
+}
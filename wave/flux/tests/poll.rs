@@ -0,0 +1,177 @@
+mod common;
+
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
+
+use common::*;
+use wave::types::{Rights, RuntimeError::*, VmCtx};
+use wave::wrappers::wasi_poll_oneoff;
+
+const SUBS: u32 = 0x1000;
+const EVENTS: u32 = 0x2000;
+const NEVENTS: u32 = 0x3000;
+
+fn write_clock(ctx: &mut VmCtx, idx: u32, userdata: u64, id: u32, timeout: u64, flags: u16) {
+    let start = (SUBS + idx * 48) as usize;
+    ctx.write_u64(start, userdata).unwrap();
+    ctx.write_u8(start + 8, 0).unwrap();
+    ctx.write_u32(start + 16, id).unwrap();
+    ctx.write_u64(start + 24, timeout).unwrap();
+    ctx.write_u64(start + 32, 0).unwrap();
+    ctx.write_u16(start + 40, flags).unwrap();
+}
+
+fn write_fd_sub(ctx: &mut VmCtx, idx: u32, userdata: u64, tag: u8, fd: u32) {
+    let start = (SUBS + idx * 48) as usize;
+    ctx.write_u64(start, userdata).unwrap();
+    ctx.write_u8(start + 8, tag).unwrap();
+    ctx.write_u32(start + 16, fd).unwrap();
+}
+
+/// (userdata, error, type, nbytes, flags) of the `idx`th event
+fn read_event(ctx: &VmCtx, idx: u32) -> (u64, u16, u8, u64, u16) {
+    let start = (EVENTS + idx * 32) as usize;
+    (
+        ctx.read_u64(start),
+        ctx.read_u16(start + 8),
+        ctx.read_u8(start + 10),
+        ctx.read_u64(start + 16),
+        ctx.read_u16(start + 24),
+    )
+}
+
+fn nevents(ctx: &VmCtx) -> u32 {
+    ctx.read_u32(NEVENTS as usize)
+}
+
+const RW: Rights = Rights::from_bits(
+    Rights::FD_READ.bits() | Rights::FD_WRITE.bits() | Rights::POLL_FD_READWRITE.bits(),
+);
+
+#[test]
+fn relative_clock_sleeps() {
+    let mut ctx = new_ctx();
+    write_clock(&mut ctx, 0, 42, 1, 20_000_000, 0);
+    let start = Instant::now();
+    assert_eq!(wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 1, NEVENTS), Ok(()));
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert_eq!(nevents(&ctx), 1);
+    assert_eq!(read_event(&ctx, 0), (42, 0, 0, 0, 0));
+}
+
+#[test]
+fn earliest_clock_fires() {
+    let mut ctx = new_ctx();
+    write_clock(&mut ctx, 0, 1, 1, 5_000_000_000, 0);
+    write_clock(&mut ctx, 1, 2, 1, 1_000_000, 0);
+    assert_eq!(wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 2, NEVENTS), Ok(()));
+    assert_eq!(nevents(&ctx), 1);
+    assert_eq!(read_event(&ctx, 0).0, 2);
+}
+
+#[test]
+fn absolute_clock_in_the_past() {
+    let mut ctx = new_ctx();
+    write_clock(&mut ctx, 0, 7, 1, 1, 1);
+    assert_eq!(wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 1, NEVENTS), Ok(()));
+    assert_eq!(nevents(&ctx), 1);
+    assert_eq!(read_event(&ctx, 0), (7, 0, 0, 0, 0));
+}
+
+#[test]
+fn fd_read_ready() {
+    let mut ctx = new_ctx();
+    let (a, mut b) = UnixStream::pair().unwrap();
+    let fd = insert_fd(&mut ctx, &a, RW);
+    b.write_all(b"hello").unwrap();
+
+    write_fd_sub(&mut ctx, 0, 9, 1, fd);
+    write_clock(&mut ctx, 1, 10, 1, 5_000_000_000, 0);
+    assert_eq!(wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 2, NEVENTS), Ok(()));
+    assert_eq!(nevents(&ctx), 1);
+    assert_eq!(read_event(&ctx, 0), (9, 0, 1, 5, 0));
+}
+
+#[test]
+fn fd_read_hangup() {
+    let mut ctx = new_ctx();
+    let (a, b) = UnixStream::pair().unwrap();
+    let fd = insert_fd(&mut ctx, &a, RW);
+    drop(b);
+
+    write_fd_sub(&mut ctx, 0, 3, 1, fd);
+    assert_eq!(wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 1, NEVENTS), Ok(()));
+    assert_eq!(nevents(&ctx), 1);
+    assert_eq!(read_event(&ctx, 0), (3, 0, 1, 0, 1));
+}
+
+#[test]
+fn fd_write_ready() {
+    let mut ctx = new_ctx();
+    let (a, _b) = UnixStream::pair().unwrap();
+    let fd = insert_fd(&mut ctx, &a, RW);
+
+    write_fd_sub(&mut ctx, 0, 4, 2, fd);
+    write_fd_sub(&mut ctx, 1, 5, 1, fd);
+    assert_eq!(wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 2, NEVENTS), Ok(()));
+    assert_eq!(nevents(&ctx), 1);
+    assert_eq!(read_event(&ctx, 0), (4, 0, 2, 0, 0));
+}
+
+#[test]
+fn bad_fds_and_clocks_fire_with_errors() {
+    let mut ctx = new_ctx();
+    let (a, _b) = UnixStream::pair().unwrap();
+    let fd = insert_fd(&mut ctx, &a, Rights::FD_READ);
+
+    write_fd_sub(&mut ctx, 0, 1, 1, 200);
+    write_fd_sub(&mut ctx, 1, 2, 1, fd);
+    write_clock(&mut ctx, 2, 3, 9, 5_000_000_000, 0);
+    assert_eq!(wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 3, NEVENTS), Ok(()));
+    assert_eq!(nevents(&ctx), 3);
+    assert_eq!(read_event(&ctx, 0), (1, Ebadf.to_wasi_errno(), 1, 0, 0));
+    assert_eq!(
+        read_event(&ctx, 1),
+        (2, Enotcapable.to_wasi_errno(), 1, 0, 0)
+    );
+    assert_eq!(read_event(&ctx, 2), (3, Einval.to_wasi_errno(), 0, 0, 0));
+}
+
+#[test]
+fn malformed_requests() {
+    let mut ctx = new_ctx();
+    assert_eq!(
+        wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 0, NEVENTS),
+        Err(Einval)
+    );
+
+    write_fd_sub(&mut ctx, 0, 1, 3, 0);
+    assert_eq!(
+        wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 1, NEVENTS),
+        Err(Einval)
+    );
+}
+
+#[test]
+fn subscription_count_checked_against_memory() {
+    let mut ctx = new_ctx();
+    write_clock(&mut ctx, 0, 1, 1, 0, 0);
+    // 48 * 100_000_000 bytes is larger than linear memory
+    assert_eq!(
+        wasi_poll_oneoff(&mut ctx, SUBS, 0, 100_000_000, NEVENTS),
+        Err(Efault)
+    );
+    assert_eq!(
+        wasi_poll_oneoff(&mut ctx, u32::MAX - 40, EVENTS, 1, NEVENTS),
+        Err(Efault)
+    );
+    assert_eq!(
+        wasi_poll_oneoff(&mut ctx, SUBS, u32::MAX - 20, 1, NEVENTS),
+        Err(Efault)
+    );
+    assert_eq!(
+        wasi_poll_oneoff(&mut ctx, SUBS, EVENTS, 1, u32::MAX - 2),
+        Err(Efault)
+    );
+}
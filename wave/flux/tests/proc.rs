@@ -0,0 +1,65 @@
+mod common;
+
+use common::*;
+use wave::types::{ExitStatus, RaisePolicy, Rights, RuntimeError::*};
+use wave::wrappers::{wasi_fd_sync, wasi_proc_exit, wasi_proc_raise, wasi_sched_yield};
+
+const SIGTERM: u8 = 15;
+
+#[test]
+fn proc_exit_records_status() {
+    let mut ctx = new_ctx();
+    assert_eq!(ctx.exit_status, None);
+    assert_eq!(wasi_proc_exit(&mut ctx, 3), Err(Exited));
+    assert_eq!(ctx.exit_status, Some(ExitStatus::Exited(3)));
+    assert_eq!(ctx.exit_status.unwrap().code(), 3);
+}
+
+#[test]
+fn exited_guest_cannot_call_wrappers() {
+    let mut ctx = new_ctx();
+    let file = temp_file(b"");
+    let fd = insert_fd(&mut ctx, &file, Rights::FD_SYNC);
+    assert_eq!(wasi_fd_sync(&ctx, fd), Ok(()));
+
+    assert_eq!(wasi_proc_exit(&mut ctx, 3), Err(Exited));
+    assert_eq!(wasi_fd_sync(&ctx, fd), Err(Exited));
+    assert_eq!(wasi_sched_yield(&ctx), Err(Exited));
+    // the first exit status sticks
+    assert_eq!(wasi_proc_exit(&mut ctx, 4), Err(Exited));
+    ctx.raise_policy = RaisePolicy::Terminate;
+    assert_eq!(wasi_proc_raise(&mut ctx, SIGTERM), Err(Exited));
+    assert_eq!(ctx.exit_status, Some(ExitStatus::Exited(3)));
+}
+
+#[test]
+fn proc_raise_follows_policy() {
+    let mut ctx = new_ctx();
+    assert_eq!(ctx.raise_policy, RaisePolicy::Deny);
+    assert_eq!(wasi_proc_raise(&mut ctx, SIGTERM), Err(Enotsup));
+
+    ctx.raise_policy = RaisePolicy::Ignore;
+    assert_eq!(wasi_proc_raise(&mut ctx, SIGTERM), Ok(()));
+    assert_eq!(ctx.exit_status, None);
+
+    ctx.raise_policy = RaisePolicy::Terminate;
+    assert_eq!(wasi_proc_raise(&mut ctx, SIGTERM), Err(Exited));
+    assert_eq!(ctx.exit_status, Some(ExitStatus::Signaled(SIGTERM)));
+    assert_eq!(ctx.exit_status.unwrap().code(), 128 + 15);
+}
+
+#[test]
+fn proc_raise_rejects_unknown_signals() {
+    let mut ctx = new_ctx();
+    ctx.raise_policy = RaisePolicy::Terminate;
+    for sig in [0, 31, u8::MAX] {
+        assert_eq!(wasi_proc_raise(&mut ctx, sig), Err(Einval), "{}", sig);
+    }
+    assert_eq!(ctx.exit_status, None);
+}
+
+#[test]
+fn sched_yield() {
+    let ctx = new_ctx();
+    assert_eq!(wasi_sched_yield(&ctx), Ok(()));
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use std::io::{Read, Seek, SeekFrom};
+
+use common::*;
+use wave::quota::{Quota, QuotaLimits, QuotaUsage};
+use wave::types::{HostFd, Rights, RuntimeError::*, VmCtx};
+use wave::wrappers::wasi_fd_write;
+
+const IOVS: u32 = 0x100;
+const DATA: u32 = 0x1000;
+const NWRITTEN: u32 = 0x2000;
+
+fn ctx_with(limits: QuotaLimits) -> VmCtx {
+    let mut ctx = new_ctx();
+    ctx.quota = Quota::new(limits);
+    ctx
+}
+
+/// Lay out `lens.len()` iovecs pointing at consecutive chunks of DATA.
+fn write_buffers(ctx: &mut VmCtx, lens: &[u32]) {
+    let iovs = packed_iovs(DATA, lens);
+    write_iovs(ctx, IOVS, &iovs);
+    for (base, len) in iovs {
+        for j in 0..len {
+            ctx.write_u8((base + j) as usize, b'a' + (j % 26) as u8)
+                .unwrap();
+        }
+    }
+}
+
+#[test]
+fn open_fd_limit() {
+    let mut ctx = ctx_with(QuotaLimits {
+        max_open_fds: 2,
+        ..Default::default()
+    });
+    let file = temp_file(b"");
+    let host = dirfd(&file);
+    assert_eq!(ctx.create_fd(host, Rights::FD_READ), Ok(0));
+    assert_eq!(ctx.create_fd(host, Rights::FD_READ), Ok(1));
+    assert_eq!(ctx.create_fd(host, Rights::FD_READ), Err(Emfile));
+    assert_eq!(ctx.quota_usage().open_fds, 2);
+}
+
+#[test]
+fn write_goes_through() {
+    let mut ctx = new_ctx();
+    let mut file = temp_file(b"");
+    let fd = insert_fd(&mut ctx, &file, Rights::FD_WRITE);
+    write_buffers(&mut ctx, &[3, 0, 4]);
+    assert_eq!(wasi_fd_write(&mut ctx, fd, IOVS, 3, NWRITTEN), Ok(()));
+    assert_eq!(ctx.read_u32(NWRITTEN as usize), 7);
+
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, "abcabcd");
+}
+
+#[test]
+fn per_fd_write_limit() {
+    let mut ctx = ctx_with(QuotaLimits {
+        max_bytes_written_per_fd: 10,
+        ..Default::default()
+    });
+    let a = temp_file(b"");
+    let b = temp_file(b"");
+    let fd_a = insert_fd(&mut ctx, &a, Rights::FD_WRITE);
+    let fd_b = insert_fd(&mut ctx, &b, Rights::FD_WRITE);
+    write_buffers(&mut ctx, &[6]);
+
+    assert_eq!(wasi_fd_write(&mut ctx, fd_a, IOVS, 1, NWRITTEN), Ok(()));
+    assert_eq!(
+        wasi_fd_write(&mut ctx, fd_a, IOVS, 1, NWRITTEN),
+        Err(Edquot)
+    );
+    assert_eq!(wasi_fd_write(&mut ctx, fd_b, IOVS, 1, NWRITTEN), Ok(()));
+    // nothing was written by the rejected call
+    assert_eq!(a.metadata().unwrap().len(), 6);
+    assert_eq!(ctx.quota.bytes_written_to(fd_a), 6);
+    assert_eq!(ctx.quota.bytes_written_to(fd_b), 6);
+}
+
+#[test]
+fn total_write_limit() {
+    let mut ctx = ctx_with(QuotaLimits {
+        max_bytes_written: 10,
+        ..Default::default()
+    });
+    let a = temp_file(b"");
+    let b = temp_file(b"");
+    let fd_a = insert_fd(&mut ctx, &a, Rights::FD_WRITE);
+    let fd_b = insert_fd(&mut ctx, &b, Rights::FD_WRITE);
+    write_buffers(&mut ctx, &[5]);
+
+    assert_eq!(wasi_fd_write(&mut ctx, fd_a, IOVS, 1, NWRITTEN), Ok(()));
+    assert_eq!(wasi_fd_write(&mut ctx, fd_b, IOVS, 1, NWRITTEN), Ok(()));
+    assert_eq!(
+        wasi_fd_write(&mut ctx, fd_a, IOVS, 1, NWRITTEN),
+        Err(Edquot)
+    );
+    assert_eq!(
+        ctx.quota_usage(),
+        QuotaUsage {
+            open_fds: 2,
+            bytes_written: 10,
+            path_resolutions: 0,
+        }
+    );
+}
+
+#[test]
+fn iovec_limit() {
+    let mut ctx = ctx_with(QuotaLimits {
+        max_iovecs: 2,
+        ..Default::default()
+    });
+    let file = temp_file(b"");
+    let fd = insert_fd(&mut ctx, &file, Rights::FD_WRITE);
+    write_buffers(&mut ctx, &[1, 1, 1]);
+    assert_eq!(wasi_fd_write(&mut ctx, fd, IOVS, 2, NWRITTEN), Ok(()));
+    assert_eq!(wasi_fd_write(&mut ctx, fd, IOVS, 3, NWRITTEN), Err(Einval));
+}
+
+#[test]
+fn path_resolution_rate() {
+    let mut quota = Quota::new(QuotaLimits {
+        max_path_resolutions_per_sec: 2,
+        ..Default::default()
+    });
+    let sec = 1_000_000_000;
+    assert_eq!(quota.charge_path_resolution(5 * sec), Ok(()));
+    assert_eq!(quota.charge_path_resolution(5 * sec + 1), Ok(()));
+    assert_eq!(quota.charge_path_resolution(5 * sec + 2), Err(Eagain));
+    assert_eq!(quota.charge_path_resolution(6 * sec - 1), Err(Eagain));
+    // a new window starts a second after the previous one
+    assert_eq!(quota.charge_path_resolution(6 * sec), Ok(()));
+}
+
+#[test]
+fn translate_path_is_rate_limited() {
+    let mut ctx = ctx_with(QuotaLimits {
+        max_path_resolutions_per_sec: 0,
+        ..Default::default()
+    });
+    let path = bytes(b"a/b");
+    ctx.copy_buf_to_sandbox(DATA, &path, 3).unwrap();
+    let r = ctx.translate_path(DATA, 3, true, HostFd::from_raw(0));
+    assert!(matches!(r, Err(Eagain)));
+    assert_eq!(ctx.quota_usage().path_resolutions, 0);
+}
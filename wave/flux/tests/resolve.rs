@@ -0,0 +1,131 @@
+mod common;
+
+use std::fs::{self, File};
+
+use common::*;
+use wave::path_resolution::{resolve_fd, Resolver};
+use wave::tcb::os::{openat2_available, os_close, os_openat2_beneath};
+use wave::types::{RuntimeError, RuntimeError::*};
+
+fn resolve(
+    dir: &File,
+    path: &str,
+    should_follow: bool,
+    resolver: Resolver,
+) -> Result<(), RuntimeError> {
+    let dirfd = dirfd(dir);
+    let path = bytes(path.as_bytes());
+    let fd = resolve_fd(path, should_follow, dirfd, resolver)?;
+    os_close(fd)
+}
+
+/// The backends to compare. `Openat2` quietly falls back to `Manual` on
+/// hosts without openat2, so it is only included when the kernel has it.
+fn backends(dir: &File) -> Vec<Resolver> {
+    // availability is only learned from a first call
+    let dirfd = dirfd(dir);
+    match os_openat2_beneath(dirfd, b".", true) {
+        Ok(fd) => os_close(fd).unwrap(),
+        Err(Enosys) => {}
+        Err(e) => panic!("openat2 probe failed: {:?}", e),
+    }
+    if openat2_available() {
+        vec![Resolver::Manual, Resolver::Openat2]
+    } else {
+        eprintln!("host has no openat2, only testing the manual backend");
+        vec![Resolver::Manual]
+    }
+}
+
+// (path, should_follow, accepted)
+const CASES: &[(&str, bool, bool)] = &[
+    (".", true, true),
+    ("dir", true, true),
+    ("dir/file", true, true),
+    ("dir/../other", true, true),
+    ("dir/up/other", true, true),
+    ("dir/up/dir/up/dir/sib", true, true),
+    ("dir/sib", true, true),
+    ("c0", true, true),
+    ("c0", false, true),
+    ("abs", false, true),
+    ("loop1", false, true),
+    ("dangling", false, true),
+    ("dir/sub/back/file", true, true),
+    ("chain", true, true),
+    ("up", false, true),
+    ("", true, false),
+    ("..", true, false),
+    ("../x", true, false),
+    ("dir/../..", true, false),
+    ("/etc", true, false),
+    ("/etc/passwd", false, false),
+    ("dir/upup", true, false),
+    ("dir/upup/x", false, false),
+    ("dir/up/..", true, false),
+    ("abs", true, false),
+    ("abs/passwd", false, false),
+    ("abs_file", true, false),
+    ("escape", true, false),
+    ("escape/x", false, false),
+    ("proc", true, false),
+    ("proc/etc", false, false),
+    ("c_out", true, false),
+    ("loop1", true, false),
+    ("self", true, false),
+    ("self/x", false, false),
+    ("dangling", true, false),
+    ("missing/../other", true, false),
+    ("up", true, false),
+    ("up/x", false, false),
+    ("dangling_up", true, false),
+    ("other/x", true, false),
+    ("dir\0/file", true, false),
+];
+
+#[test]
+fn backends_agree_on_hostile_trees() {
+    let (root, dir) = hostile_tree();
+    for resolver in backends(&dir) {
+        for (path, should_follow, expected) in CASES {
+            assert_eq!(
+                resolve(&dir, path, *should_follow, resolver).is_ok(),
+                *expected,
+                "{:?}: {:?} follow={}",
+                resolver,
+                path,
+                should_follow
+            );
+        }
+    }
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn escapes_are_not_capable() {
+    let (root, dir) = hostile_tree();
+    let resolvers = backends(&dir);
+    for path in ["..", "/etc", "dir/upup", "abs", "escape", "dir/up/.."] {
+        for resolver in &resolvers {
+            assert_eq!(resolve(&dir, path, true, *resolver), Err(Enotcapable));
+        }
+    }
+    for resolver in resolvers {
+        assert_eq!(resolve(&dir, "self", true, resolver), Err(Eloop));
+    }
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn resolved_fd_names_the_target() {
+    let (root, dir) = hostile_tree();
+    let dirfd = dirfd(&dir);
+    for resolver in backends(&dir) {
+        let path = bytes(b"dir/sib");
+        let fd = resolve_fd(path, true, dirfd, resolver).unwrap();
+        let target = fs::read_link(format!("/proc/self/fd/{}", fd.to_raw())).unwrap();
+        assert_eq!(target, root.join("other").canonicalize().unwrap());
+        os_close(fd).unwrap();
+    }
+    fs::remove_dir_all(root).unwrap();
+}
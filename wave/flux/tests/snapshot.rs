@@ -0,0 +1,129 @@
+mod common;
+
+use common::*;
+use wave::rvec::RVec;
+use wave::snapshot::PAGE_SIZE;
+use wave::types::{ExitStatus, RuntimeError::*, VmCtx, LINEAR_MEM_SIZE};
+use wave::wrappers::{wasi_proc_exit, wasi_sched_yield};
+
+fn read(ctx: &VmCtx, start: u32, n: u32) -> Vec<u8> {
+    ctx.copy_buf_from_sandbox(start, n).to_vec()
+}
+
+#[test]
+fn snapshot_stores_only_written_pages() {
+    let mut ctx = new_ctx();
+    ctx.write_u32(10, 0xdeadbeef).unwrap();
+    ctx.write_u64(3 * PAGE_SIZE - 4, u64::MAX).unwrap(); // straddles pages 2 and 3
+    let snap = ctx.snapshot();
+    assert_eq!(snap.page_count(), 3);
+    assert_eq!(ctx.dirty_page_count(), 0);
+}
+
+#[test]
+fn restore_rewrites_only_dirty_pages() {
+    let mut ctx = new_ctx();
+    ctx.copy_buf_to_sandbox(100, &bytes(b"hello"), 5).unwrap();
+    ctx.write_u8(5 * PAGE_SIZE, 1).unwrap();
+    let snap = ctx.snapshot();
+
+    for round in 0..3u8 {
+        ctx.copy_buf_to_sandbox(100, &bytes(b"HELLO"), 5).unwrap();
+        ctx.write_u16(9 * PAGE_SIZE + 7, 0xffff).unwrap();
+        ctx.write_u8(9 * PAGE_SIZE + 100, round).unwrap();
+        assert_eq!(ctx.dirty_page_count(), 2);
+
+        assert_eq!(ctx.restore(&snap), 2);
+        assert_eq!(read(&ctx, 100, 5), b"hello");
+        assert_eq!(ctx.read_u8(5 * PAGE_SIZE), 1);
+        assert_eq!(ctx.read_u16(9 * PAGE_SIZE + 7), 0);
+        assert_eq!(ctx.read_u8(9 * PAGE_SIZE + 100), 0);
+        assert_eq!(ctx.dirty_page_count(), 0);
+    }
+}
+
+#[test]
+fn restore_older_snapshot() {
+    let mut ctx = new_ctx();
+    ctx.write_u8(0, 1).unwrap();
+    let first = ctx.snapshot();
+    ctx.write_u8(0, 2).unwrap();
+    ctx.write_u8(PAGE_SIZE, 2).unwrap();
+    let second = ctx.snapshot();
+    ctx.write_u8(2 * PAGE_SIZE, 3).unwrap();
+
+    // pages dirtied before `second` must also be rolled back
+    assert_eq!(ctx.restore(&first), 3);
+    assert_eq!(ctx.read_u8(0), 1);
+    assert_eq!(ctx.read_u8(PAGE_SIZE), 0);
+    assert_eq!(ctx.read_u8(2 * PAGE_SIZE), 0);
+
+    ctx.restore(&second);
+    assert_eq!(ctx.read_u8(0), 2);
+    assert_eq!(ctx.read_u8(PAGE_SIZE), 2);
+    assert_eq!(ctx.read_u8(2 * PAGE_SIZE), 0);
+}
+
+#[test]
+fn restore_into_another_context() {
+    let mut ctx = new_ctx();
+    ctx.copy_buf_to_sandbox(4 * PAGE_SIZE as u32, &bytes(b"abc"), 3)
+        .unwrap();
+    let snap = ctx.snapshot();
+
+    let mut other = new_ctx();
+    other.write_u8(7 * PAGE_SIZE, 9).unwrap();
+    assert_eq!(other.restore(&snap), 2);
+    assert_eq!(read(&other, 4 * PAGE_SIZE as u32, 3), b"abc");
+    assert_eq!(other.read_u8(7 * PAGE_SIZE), 0);
+}
+
+#[test]
+fn last_partial_page() {
+    let mut ctx = new_ctx();
+    let last = LINEAR_MEM_SIZE - 2;
+    ctx.write_u8(last, 0xaa).unwrap();
+    let snap = ctx.snapshot();
+    ctx.write_u8(last, 0xbb).unwrap();
+    assert_eq!(ctx.restore(&snap), 1);
+    assert_eq!(ctx.read_u8(last), 0xaa);
+}
+
+#[test]
+fn restore_arg_and_env_buffers() {
+    let mut ctx = VmCtx::new(bytes(b"prog\0-v\0"), 2, bytes(b"A=1\0"), 1);
+    let snap = ctx.snapshot();
+
+    ctx.arg_buffer = bytes(b"other\0");
+    ctx.argc = 1;
+    ctx.env_buffer = RVec::new();
+    ctx.envc = 0;
+
+    ctx.restore(&snap);
+    assert_eq!(ctx.arg_buffer.clone().to_vec(), b"prog\0-v\0");
+    assert_eq!(ctx.argc, 2);
+    assert_eq!(ctx.env_buffer.clone().to_vec(), b"A=1\0");
+    assert_eq!(ctx.envc, 1);
+    assert_eq!(ctx.memlen, LINEAR_MEM_SIZE);
+    assert_eq!(ctx.copy_arg_buffer_to_sandbox(0, 8), Ok(()));
+    assert_eq!(read(&ctx, 0, 8), b"prog\0-v\0");
+}
+
+#[test]
+fn restore_undoes_exit() {
+    let mut ctx = new_ctx();
+    let snap = ctx.snapshot();
+    assert_eq!(wasi_proc_exit(&mut ctx, 3), Err(Exited));
+    assert_eq!(wasi_sched_yield(&ctx), Err(Exited));
+
+    ctx.restore(&snap);
+    assert_eq!(ctx.exit_status, None);
+    assert_eq!(wasi_sched_yield(&ctx), Ok(()));
+
+    // a snapshot taken after the exit keeps it
+    assert_eq!(wasi_proc_exit(&mut ctx, 4), Err(Exited));
+    let exited = ctx.snapshot();
+    ctx.restore(&snap);
+    ctx.restore(&exited);
+    assert_eq!(ctx.exit_status, Some(ExitStatus::Exited(4)));
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream, UdpSocket};
+
+use common::*;
+use wave::guard::GuardKind;
+use wave::tcb::path::{NetEndpoint, WasiProto};
+use wave::types::{RuntimeError::*, VmCtx};
+use wave::wrappers::{
+    wasi_sock_accept, wasi_sock_connect, wasi_sock_listen, wasi_sock_recv, wasi_sock_send,
+};
+
+const IOVS: u32 = 0x100;
+const DATA: u32 = 0x1000;
+const DATALEN: u32 = 0x2000;
+const FLAGS: u32 = 0x2010;
+const RO_FD: u32 = 0x2020;
+
+const PEEK: u16 = 1;
+const WAITALL: u16 = 2;
+const TRUNCATED: u16 = 1;
+
+fn loopback(protocol: WasiProto, port: u16) -> NetEndpoint {
+    NetEndpoint {
+        protocol,
+        addr: u32::from(Ipv4Addr::LOCALHOST),
+        port: port as u32,
+    }
+}
+
+/// Lay out iovecs covering consecutive `lens`-sized buffers from `DATA`.
+fn guest_bytes(ctx: &VmCtx, len: u32) -> Vec<u8> {
+    ctx.read_guest_bytes(DATA, len).unwrap().to_vec()
+}
+
+#[test]
+fn connect_needs_allowlist() {
+    let mut ctx = new_ctx();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let endpoint = loopback(WasiProto::Tcp, port);
+    assert_eq!(wasi_sock_connect(&mut ctx, endpoint), Err(Enotcapable));
+    // the protocol is part of the allowlist entry
+    ctx.netlist.push(loopback(WasiProto::Udp, port));
+    assert_eq!(wasi_sock_connect(&mut ctx, endpoint), Err(Enotcapable));
+    assert_eq!(wasi_sock_listen(&mut ctx, endpoint, 1), Err(Enotcapable));
+
+    let mut too_big = endpoint;
+    too_big.port = 1 << 16;
+    ctx.netlist.push(too_big);
+    assert_eq!(wasi_sock_connect(&mut ctx, too_big), Err(Einval));
+    assert_eq!(ctx.fdmap.open_count(), 0);
+}
+
+#[test]
+fn tcp_send_and_recv() {
+    let mut ctx = new_ctx();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = loopback(WasiProto::Tcp, listener.local_addr().unwrap().port());
+    ctx.netlist.push(endpoint);
+    let fd = wasi_sock_connect(&mut ctx, endpoint).unwrap();
+    let (mut peer, _) = listener.accept().unwrap();
+
+    ctx.copy_buf_to_sandbox(DATA, &bytes(b"hello world"), 11)
+        .unwrap();
+    write_iovs(&mut ctx, IOVS, &packed_iovs(DATA, &[6, 5]));
+    assert_eq!(wasi_sock_send(&mut ctx, fd, IOVS, 2, 0, DATALEN), Ok(()));
+    assert_eq!(ctx.read_u32(DATALEN as usize), 11);
+    let mut buf = [0; 11];
+    peer.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello world");
+    assert_eq!(
+        wasi_sock_send(&mut ctx, fd, IOVS, 2, 1, DATALEN),
+        Err(Einval)
+    );
+
+    peer.write_all(b"ping!").unwrap();
+    write_iovs(&mut ctx, IOVS, &packed_iovs(DATA, &[2, 3]));
+    // peeking leaves the data in the socket for the next receive
+    assert_eq!(
+        wasi_sock_recv(&mut ctx, fd, IOVS, 2, PEEK | WAITALL, DATALEN, FLAGS),
+        Ok(())
+    );
+    assert_eq!(ctx.read_u32(DATALEN as usize), 5);
+    assert_eq!(guest_bytes(&ctx, 5), b"ping!");
+    ctx.copy_buf_to_sandbox(DATA, &bytes(b"....."), 5).unwrap();
+    assert_eq!(
+        wasi_sock_recv(&mut ctx, fd, IOVS, 2, WAITALL, DATALEN, FLAGS),
+        Ok(())
+    );
+    assert_eq!(ctx.read_u32(DATALEN as usize), 5);
+    assert_eq!(ctx.read_u16(FLAGS as usize), 0);
+    assert_eq!(guest_bytes(&ctx, 5), b"ping!");
+
+    assert_eq!(
+        wasi_sock_recv(&mut ctx, fd, IOVS, 2, 4, DATALEN, FLAGS),
+        Err(Einval)
+    );
+    // receive buffers must be writable
+    ctx.add_guard(DATA as usize + 3, 1, GuardKind::ReadOnly)
+        .unwrap();
+    assert_eq!(
+        wasi_sock_recv(&mut ctx, fd, IOVS, 2, 0, DATALEN, FLAGS),
+        Err(Efault)
+    );
+}
+
+#[test]
+fn listen_and_accept() {
+    let mut ctx = new_ctx();
+    // find a free port, then let the sandbox listen on it
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let endpoint = loopback(WasiProto::Tcp, port);
+    ctx.netlist.push(endpoint);
+    ctx.netlist.push(loopback(WasiProto::Udp, port));
+    assert_eq!(
+        wasi_sock_listen(&mut ctx, loopback(WasiProto::Udp, port), 1),
+        Err(Enotsup)
+    );
+    let listen_fd = wasi_sock_listen(&mut ctx, endpoint, 4).unwrap();
+
+    let mut client = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    assert_eq!(wasi_sock_accept(&mut ctx, listen_fd, 1, RO_FD), Err(Einval));
+    assert_eq!(wasi_sock_accept(&mut ctx, listen_fd, 0, RO_FD), Ok(()));
+    let conn = ctx.read_u32(RO_FD as usize);
+    assert_ne!(conn, listen_fd);
+
+    // only listening sockets accept, and they cannot be read or written
+    assert_eq!(wasi_sock_accept(&mut ctx, conn, 0, RO_FD), Err(Enotcapable));
+    write_iovs(&mut ctx, IOVS, &packed_iovs(DATA, &[3]));
+    assert_eq!(
+        wasi_sock_send(&mut ctx, listen_fd, IOVS, 1, 0, DATALEN),
+        Err(Enotcapable)
+    );
+
+    ctx.copy_buf_to_sandbox(DATA, &bytes(b"hey"), 3).unwrap();
+    assert_eq!(wasi_sock_send(&mut ctx, conn, IOVS, 1, 0, DATALEN), Ok(()));
+    let mut buf = [0; 3];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hey");
+
+    // `nonblock` applies to the accepted socket
+    let _second = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    assert_eq!(wasi_sock_accept(&mut ctx, listen_fd, 4, RO_FD), Ok(()));
+    let nonblocking = ctx.read_u32(RO_FD as usize);
+    assert_eq!(
+        wasi_sock_recv(&mut ctx, nonblocking, IOVS, 1, 0, DATALEN, FLAGS),
+        Err(Eagain)
+    );
+}
+
+#[test]
+fn udp_truncation_is_reported() {
+    let mut ctx = new_ctx();
+    let peer = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let endpoint = loopback(WasiProto::Udp, peer.local_addr().unwrap().port());
+    ctx.netlist.push(endpoint);
+    let fd = wasi_sock_connect(&mut ctx, endpoint).unwrap();
+
+    ctx.copy_buf_to_sandbox(DATA, &bytes(b"hi"), 2).unwrap();
+    write_iovs(&mut ctx, IOVS, &packed_iovs(DATA, &[2]));
+    assert_eq!(wasi_sock_send(&mut ctx, fd, IOVS, 1, 0, DATALEN), Ok(()));
+    let mut buf = [0; 16];
+    let (n, from) = peer.recv_from(&mut buf).unwrap();
+    assert_eq!(&buf[..n], b"hi");
+
+    peer.send_to(b"0123456789", from).unwrap();
+    write_iovs(&mut ctx, IOVS, &packed_iovs(DATA, &[4]));
+    assert_eq!(
+        wasi_sock_recv(&mut ctx, fd, IOVS, 1, 0, DATALEN, FLAGS),
+        Ok(())
+    );
+    assert_eq!(ctx.read_u32(DATALEN as usize), 4);
+    assert_eq!(ctx.read_u16(FLAGS as usize), TRUNCATED);
+    assert_eq!(guest_bytes(&ctx, 4), b"0123");
+}
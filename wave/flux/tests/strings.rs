@@ -0,0 +1,69 @@
+mod common;
+
+use common::*;
+use wave::types::{RuntimeError::*, VmCtx, LINEAR_MEM_SIZE};
+
+const STR: u32 = 0x100;
+
+fn write_bytes(ctx: &mut VmCtx, start: usize, bytes: &[u8]) {
+    for (i, b) in bytes.iter().enumerate() {
+        ctx.write_u8(start + i, *b).unwrap();
+    }
+}
+
+#[test]
+fn length_prefixed() {
+    let mut ctx = new_ctx();
+    write_bytes(&mut ctx, STR as usize, b"hello\0world");
+    assert_eq!(
+        ctx.read_guest_bytes(STR, 11).unwrap().to_vec(),
+        b"hello\0world"
+    );
+    assert_eq!(ctx.read_guest_bytes(STR, 0).unwrap().len(), 0);
+    assert_eq!(ctx.read_guest_str(STR, 5), Ok("hello".to_string()));
+
+    let end = LINEAR_MEM_SIZE as u32;
+    assert!(matches!(ctx.read_guest_bytes(end - 4, 4), Err(Efault)));
+    assert!(matches!(ctx.read_guest_bytes(end, 0), Err(Efault)));
+    assert!(matches!(ctx.read_guest_bytes(u32::MAX, 1), Err(Efault)));
+    assert_eq!(ctx.read_guest_str(u32::MAX - 1, 2), Err(Efault));
+}
+
+#[test]
+fn nul_terminated() {
+    let mut ctx = new_ctx();
+    write_bytes(&mut ctx, STR as usize, b"path/to\0rest");
+    assert_eq!(ctx.read_guest_cstr(STR, 64).unwrap().to_vec(), b"path/to");
+    assert_eq!(ctx.read_guest_cstr_str(STR, 8), Ok("path/to".to_string()));
+    // the terminator must be within the first `max_len` bytes
+    assert!(matches!(ctx.read_guest_cstr(STR, 7), Err(Enametoolong)));
+    assert!(matches!(ctx.read_guest_cstr(STR, 0), Err(Enametoolong)));
+    assert_eq!(ctx.read_guest_cstr_str(STR + 7, 1), Ok(String::new()));
+}
+
+#[test]
+fn scan_stops_at_end_of_memory() {
+    let mut ctx = new_ctx();
+    let last = LINEAR_MEM_SIZE - 3;
+    write_bytes(&mut ctx, last, b"abc");
+    assert!(matches!(
+        ctx.read_guest_cstr(last as u32, u32::MAX),
+        Err(Efault)
+    ));
+    assert_eq!(ctx.read_guest_cstr_str(u32::MAX, 16), Err(Efault));
+    // hitting `max_len` first is still a length error
+    assert!(matches!(
+        ctx.read_guest_cstr(last as u32, 2),
+        Err(Enametoolong)
+    ));
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut ctx = new_ctx();
+    write_bytes(&mut ctx, STR as usize, b"ab\xff\xfe\0");
+    assert_eq!(ctx.read_guest_cstr(STR, 16).unwrap().len(), 4);
+    assert_eq!(ctx.read_guest_cstr_str(STR, 16), Err(Eilseq));
+    assert_eq!(ctx.read_guest_str(STR, 3), Err(Eilseq));
+    assert_eq!(ctx.read_guest_str(STR, 2), Ok("ab".to_string()));
+}
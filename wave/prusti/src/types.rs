@@ -109,12 +109,11 @@ pub struct VmCtx {
 pub struct HostFd(usize);
 
 impl HostFd {
-    pub(crate) fn to_raw(&self) -> usize {
+    pub fn to_raw(&self) -> usize {
         self.0
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_raw(w: usize) -> HostFd {
+    pub fn from_raw(w: usize) -> HostFd {
         HostFd(w)
     }
 }